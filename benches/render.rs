@@ -0,0 +1,98 @@
+// Copyright (C) 2023 Sebastien Guerri
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Rendering time of growing inputs, run with `cargo bench`
+//!
+//! Each input is twice as big as the previous one: the time per byte should stay flat.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use colored_str::*;
+
+/// Number of runs of each measure, the fastest one being kept
+const RUNS: usize = 5;
+
+/// Number of lines of the smallest input
+const LINES: usize = 2_000;
+
+/// Number of doublings of the input
+const STEPS: u32 = 5;
+
+/// Builds a log dump of given number of lines
+fn log_dump(lines: usize) -> String
+{
+    let mut text = String::new();
+    for id in 0..lines {
+        let line = match id % 4 {
+            0 => format!("<dimmed>{:06}</> <green>INFO</> request <bold>#{}</> served\n", id, id),
+            1 => format!("<dimmed>{:06}</> <yellow+bold>WARN <+underline>slow \\<{}ms><-> on</> <#FF8000>cache</>\n", id, id % 97),
+            2 => format!("<dimmed>{:06}</> <red>ERROR <+on_white>code {}<-> unknown <rde>style</></>\n", id, id % 13),
+            _ => format!("<dimmed>{:06}</> <c208>DEBUG</> plain text with an unmatched <blue> flag\n", id)
+        };
+        text.push_str(&line);
+    }
+    text
+}
+
+/// Returns the fastest time to run given function
+fn measure(mut f: impl FnMut()) -> Duration
+{
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+/// Measures given renderer on inputs of growing size
+fn bench(name: &str, mut render: impl FnMut(&str))
+{
+    println!("{}", name);
+    let mut previous: Option<f64> = None;
+    for step in 0..STEPS {
+        let text = log_dump(LINES << step);
+        let time = measure(|| render(black_box(&text)));
+        let per_byte = time.as_nanos() as f64 / text.len() as f64;
+        let ratio = previous.map(|previous| format!("x{:.2}", per_byte / previous)).unwrap_or_default();
+        println!("  {:>10} bytes {:>10.3} ms {:>8.2} ns/byte {}", text.len(), time.as_secs_f64() * 1000.0, per_byte, ratio);
+        previous = Some(per_byte);
+    }
+}
+
+fn main()
+{
+    set_color_choice(ColorChoice::Always);
+    set_color_depth(Some(ColorDepth::TrueColor));
+
+    // The underline code is only written if the variation of the sample is rendered
+    let sample = colored_for(&log_dump(4), Stream::Stdout).to_string();
+    assert!(sample.contains("\x1B[4mslow <"), "the variation of the sample is not rendered");
+
+    bench("colored_for", |text| {
+        black_box(colored_for(text, Stream::Stdout));
+    });
+    bench("write_colored", |text| {
+        let mut result = String::with_capacity(text.len() * 2);
+        write_colored(&mut result, text, Stream::Stdout).unwrap();
+        black_box(result);
+    });
+    bench("strip", |text| {
+        black_box(strip(text));
+    });
+}
@@ -0,0 +1,221 @@
+// Copyright (C) 2023 Sebastien Guerri
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Markup rendering of literal format strings, at compile time
+
+use colored_str_syntax::{decoration_index, unescape, COLORS};
+
+use crate::check::{is_index, read_flags, FlagKind, PLACEHOLDER};
+
+/// Color depth a baked format string needs, in increasing order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Depth
+{
+    /// Decorations only
+    NoColor,
+    /// The 16 terminal colors
+    Ansi16,
+    /// The 256 colors palette
+    Ansi256,
+    /// Truecolors
+    TrueColor,
+}
+
+impl Depth
+{
+    /// Name of the matching `ColorDepth` variant
+    pub fn name(self) -> &'static str
+    {
+        match self {
+            Depth::NoColor => "NoColor",
+            Depth::Ansi16 => "Ansi16",
+            Depth::Ansi256 => "Ansi256",
+            Depth::TrueColor => "TrueColor"
+        }
+    }
+}
+
+/// Style of a piece of text
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Style
+{
+    /// Foreground color code, if any
+    pub fg: Option<String>,
+    /// Background color code, if any
+    pub bg: Option<String>,
+    /// Whether each decoration of [`DECORATIONS`](colored_str_syntax::DECORATIONS) is set
+    pub decorations: [bool; 8],
+}
+
+impl Style
+{
+    /// Decorations, as bits in the order of [`DECORATIONS`](colored_str_syntax::DECORATIONS)
+    pub fn decoration_bits(&self) -> u8
+    {
+        self.decorations.iter().enumerate()
+            .filter(|(_, set)| **set)
+            .fold(0, |bits, (id, _)| bits | 1 << id)
+    }
+}
+
+/// A piece of the text of a baked segment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Part
+{
+    /// Literal text
+    Text(String),
+    /// Next placeholder of the format string
+    Placeholder,
+}
+
+/// Text sharing a style, as baked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Segment
+{
+    /// Style of the text
+    pub style: Style,
+    /// Pieces of the text
+    pub parts: Vec<Part>,
+}
+
+/// Returns the code of a color, recording the color depth it needs
+///
+/// Returns `None` for colors that are not baked, such as named colors and color functions.
+fn color_code(color: &str, background: bool, depth: &mut Depth) -> Option<String>
+{
+    let (bright, name) = match color.strip_prefix('l') {
+        Some(name) if COLORS.iter().any(|(color, _)| *color == name) => (true, name),
+        _ => (false, color)
+    };
+    if let Some((_, code)) = COLORS.iter().find(|(color, _)| *color == name) {
+        *depth = (*depth).max(Depth::Ansi16);
+        let code = code + if bright { 60 } else { 0 } + if background { 10 } else { 0 };
+        return Some(code.to_string());
+    }
+
+    let layer = if background { 48 } else { 38 };
+    if let Some(index) = color.strip_prefix('c').filter(|index| is_index(index)) {
+        *depth = (*depth).max(Depth::Ansi256);
+        return Some(format!("{};5;{}", layer, index.parse::<u8>().ok()?));
+    }
+    let digits = color.strip_prefix('#')?;
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let component = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    let (r, g, b) = match digits.len() {
+        3 => {
+            let short = |id: usize| component(&digits[id..=id]).map(|value| value * 17);
+            (short(0)?, short(1)?, short(2)?)
+        },
+        6 => (component(&digits[0..2])?, component(&digits[2..4])?, component(&digits[4..6])?),
+        _ => return None
+    };
+    *depth = Depth::TrueColor;
+    Some(format!("{};2;{};{};{}", layer, r, g, b))
+}
+
+/// Apply a single style, recording the color depth it needs
+///
+/// Returns `None` for styles that are not baked, which may be defined by a theme.
+fn apply(style: &mut Style, name: &str, depth: &mut Depth) -> Option<()>
+{
+    let name = name.to_lowercase();
+    match name.as_str() {
+        "default" => style.fg = None,
+        "on_default" => style.bg = None,
+        _ => if let Some(decoration) = name.strip_prefix('!') {
+            style.decorations[decoration_index(decoration)?] = false;
+        } else if let Some(id) = decoration_index(&name) {
+            style.decorations[id] = true;
+        } else if let Some(color) = name.strip_prefix("on_") {
+            style.bg = Some(color_code(color, true, depth)?);
+        } else {
+            style.fg = Some(color_code(&name, false, depth)?);
+        }
+    }
+    Some(())
+}
+
+/// Push given text with given style to the segments, if not empty
+fn push_segment(segments: &mut Vec<Segment>, text: &str, style: &Style)
+{
+    let mut parts = vec![];
+    for (id, literal) in unescape(text).split(PLACEHOLDER).enumerate() {
+        if id > 0 {
+            parts.push(Part::Placeholder);
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Text(literal.to_string()));
+        }
+    }
+    if !parts.is_empty() {
+        segments.push(Segment { style: style.clone(), parts });
+    }
+}
+
+/// Renders the markup of a format string whose placeholders are each replaced by [`PLACEHOLDER`]
+///
+/// Returns the segments of text sharing a style and the color depth they need, or `None` if the
+/// format string can not be baked: flags that do not match, placeholders within flags, and styles
+/// that are not baked are left to the runtime.
+pub(crate) fn bake(masked: &str) -> Option<(Vec<Segment>, Depth)>
+{
+    let mut segments = vec![];
+    let mut depth = Depth::NoColor;
+    // Each entry is an opened flag, with its style
+    let mut stack: Vec<(FlagKind, Style)> = vec![];
+    let mut id = 0;
+
+    for flag in read_flags(masked) {
+        let style = stack.last().map(|(_, style)| style.clone()).unwrap_or_default();
+        push_segment(&mut segments, &masked[id..flag.span.start], &style);
+        id = flag.span.end;
+
+        let source = &masked[flag.span];
+        match flag.kind {
+            FlagKind::Open | FlagKind::VarOpen => {
+                if source.contains(PLACEHOLDER) {
+                    return None;
+                }
+                let mut style = match flag.kind {
+                    FlagKind::Open => Style::default(),
+                    _ if stack.iter().any(|(kind, _)| *kind == FlagKind::Open) => style,
+                    _ => return None
+                };
+                for name in source.trim_start_matches(['<', '+']).trim_end_matches('>').split('+') {
+                    apply(&mut style, name.trim(), &mut depth)?;
+                }
+                stack.push((flag.kind, style));
+            },
+            FlagKind::Close | FlagKind::VarClose => {
+                let open = match flag.kind {
+                    FlagKind::Close => FlagKind::Open,
+                    _ => FlagKind::VarOpen
+                };
+                match stack.pop() {
+                    Some((kind, _)) if kind == open => {},
+                    _ => return None
+                }
+            }
+        }
+    }
+
+    if !stack.is_empty() {
+        return None;
+    }
+    push_segment(&mut segments, &masked[id..], &Style::default());
+    Some((segments, depth))
+}
@@ -0,0 +1,243 @@
+// Copyright (C) 2023 Sebastien Guerri
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Markup checks of literal format strings, at compile time
+
+use std::ops::Range;
+
+use colored_str_syntax::{decoration_index, distance, read_flag, CSS_COLORS, STYLE_NAMES};
+
+pub(crate) use colored_str_syntax::FlagKind;
+
+/// Stands for placeholders in a format string, once per byte when checked, once per placeholder when baked
+pub(crate) const PLACEHOLDER: char = '\u{1}';
+
+/// A flag found while scanning a format string
+pub(crate) struct Flag
+{
+    /// Kind of the flag
+    pub kind: FlagKind,
+    /// Byte range of the flag in the format string
+    pub span: Range<usize>,
+}
+
+/// A problem found in a format string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Problem
+{
+    /// Description of the problem
+    pub message: String,
+    /// Suggestion to fix the problem, if any
+    pub help: Option<String>,
+    /// Byte range of the offending flag in the format string
+    pub span: Range<usize>,
+}
+
+/// Replace each byte of the placeholders of a format string by [`PLACEHOLDER`]
+///
+/// Returns the masked format string and whether a placeholder uses the `markup` formatting trait.
+fn mask_placeholders(template: &str) -> (String, bool)
+{
+    let mut masked = String::with_capacity(template.len());
+    let mut markup = false;
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        masked.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if rest.starts_with("{{") || rest.starts_with("}}") || rest.starts_with('}') {
+            let len = rest.len().min(2);
+            masked.push_str(&rest[..len]);
+            rest = &rest[len..];
+            continue;
+        }
+        let end = rest.find('}').map_or(rest.len(), |end| end + 1);
+        markup |= rest[..end].ends_with(":markup}");
+        masked.extend(std::iter::repeat_n(PLACEHOLDER, end));
+        rest = &rest[end..];
+    }
+    masked.push_str(rest);
+    (masked, markup)
+}
+
+/// Split given text into flags, escaped characters being skipped
+pub(crate) fn read_flags(text: &str) -> Vec<Flag>
+{
+    let mut flags = vec![];
+    let mut id = 0;
+    while let Some(pos) = text[id..].find(['<', '\\']) {
+        let start = id + pos;
+        if text[start..].starts_with('\\') {
+            id = match text[start + 1..].starts_with(['<', '\\']) {
+                true => start + 2,
+                false => start + 1
+            };
+            continue;
+        }
+        match read_flag(&text[start..], Some(PLACEHOLDER)) {
+            Some((kind, len)) => {
+                flags.push(Flag { kind, span: start..start + len });
+                id = start + len;
+            },
+            None => id = start + 1
+        }
+    }
+    flags
+}
+
+/// Check if given text is a number or a percentage
+fn is_number(text: &str) -> bool
+{
+    text.strip_suffix('%').unwrap_or(text).parse::<f64>().is_ok_and(f64::is_finite)
+}
+
+/// Check if given text is a valid color function, `ansi(N)` included
+fn is_function(text: &str) -> bool
+{
+    let Some((name, args)) = text.split_once('(') else {
+        return false;
+    };
+    let Some(args) = args.strip_suffix(')') else {
+        return false;
+    };
+    let args: Vec<&str> = args.split([',', ' ']).filter(|arg| !arg.is_empty()).collect();
+    match name.to_lowercase().as_str() {
+        "rgb" | "hsl" | "oklch" => args.len() == 3 && args.iter().all(|arg| is_number(arg)),
+        "ansi" => is_index(&args.concat()),
+        _ => false
+    }
+}
+
+/// Check if given text is an index of the 256 colors palette
+pub(crate) fn is_index(text: &str) -> bool
+{
+    !text.is_empty() && text.len() <= 3 && text.chars().all(|c| c.is_ascii_digit()) && text.parse::<u8>().is_ok()
+}
+
+/// Check if given lowercase style is a built-in style
+fn is_builtin(style: &str) -> bool
+{
+    let name = style.strip_prefix("on_").unwrap_or(style);
+    if let Some(digits) = name.strip_prefix('#') {
+        return [3, 4, 6, 8].contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_hexdigit());
+    }
+    if name.contains('(') {
+        return is_function(name);
+    }
+    STYLE_NAMES.contains(&style)
+        || style.strip_prefix('!').and_then(decoration_index).is_some()
+        || CSS_COLORS.iter().any(|(css, _)| *css == name)
+        || name.strip_prefix('c').is_some_and(is_index)
+}
+
+/// Returns the problem of a single style, if any, with a suggestion
+///
+/// Styles that are not built-in may be defined at runtime by a theme, so that only those
+/// close to a built-in style are reported, as likely typos.
+fn style_problem(style: &str) -> Option<(String, Option<String>)>
+{
+    if style.contains(PLACEHOLDER) {
+        return None;
+    }
+    let lowercase = style.to_lowercase();
+    if is_builtin(&lowercase) {
+        return None;
+    }
+    let name = lowercase.strip_prefix("on_").unwrap_or(&lowercase);
+    if name.starts_with('#') {
+        return Some((format!("invalid hexadecimal color `{}`", style), None));
+    }
+    if name.contains('(') {
+        return Some((format!("invalid color function `{}`", style), None));
+    }
+    let max = (lowercase.chars().count() / 3).max(1);
+    let removals = STYLE_NAMES.iter().filter(|name| decoration_index(name).is_some()).map(|name| format!("!{}", name));
+    let (closest, name) = STYLE_NAMES.iter().map(|name| name.to_string()).chain(removals)
+        .map(|name| (distance(&lowercase, &name), name))
+        .min_by_key(|(distance, _)| *distance)?;
+    let help = (closest <= max).then(|| format!("did you mean `{}`?", name));
+    (help.is_some() || lowercase.starts_with('!')).then(|| (format!("unknown style `{}`", style), help))
+}
+
+/// Check if a flag not matching should be reported, given its style list
+///
+/// Such flags are displayed as is, so that those holding a placeholder or a style that is
+/// neither built-in nor close to a built-in style, such as `<div>` or `<T>`, may be text.
+fn is_reported(styles: &str) -> bool
+{
+    !styles.contains(PLACEHOLDER)
+        && styles.split('+').all(|style| is_builtin(&style.trim().to_lowercase()) || style_problem(style.trim()).is_some())
+}
+
+/// Checks the markup of given format string, returning all problems found, in order of appearance.
+///
+/// Flags that do not match are only reported if no placeholder uses the `markup` formatting
+/// trait, as such arguments may hold flags, and if their styles are built-in or likely typos.
+pub(crate) fn check(template: &str) -> Vec<Problem>
+{
+    let (masked, markup) = mask_placeholders(template);
+    let flags = read_flags(&masked);
+
+    let mut problems = vec![];
+    let mut push = |message: String, help: Option<String>, span: &Range<usize>| {
+        let message = format!("{} in `{}`", message, &template[span.clone()]);
+        problems.push(Problem { message, help, span: span.clone() });
+    };
+
+    // Each entry is an opened flag, with whether to report it if it does not match
+    let mut stack: Vec<(&Flag, bool)> = vec![];
+    for flag in &flags {
+        let blocks = stack.iter().filter(|(open, _)| open.kind == FlagKind::Open).count();
+        match flag.kind {
+            FlagKind::Open | FlagKind::VarOpen => {
+                let styles = masked[flag.span.clone()].trim_start_matches(['<', '+']).trim_end_matches('>');
+                for style in styles.split('+') {
+                    if let Some((message, help)) = style_problem(style.trim()) {
+                        push(message, help, &flag.span);
+                    }
+                }
+                let reported = !markup && is_reported(styles);
+                if flag.kind == FlagKind::VarOpen && blocks == 0 && !markup {
+                    if reported {
+                        push("variation outside of a block".to_string(), None, &flag.span);
+                    }
+                } else {
+                    stack.push((flag, reported));
+                }
+            },
+            FlagKind::Close if blocks > 0 => {
+                while let Some((open, reported)) = stack.pop() {
+                    if open.kind == FlagKind::Open {
+                        break;
+                    }
+                    if reported {
+                        push("unclosed block".to_string(), None, &open.span);
+                    }
+                }
+            },
+            FlagKind::VarClose if stack.last().is_some_and(|(open, _)| open.kind == FlagKind::VarOpen) => {
+                stack.pop();
+            },
+            FlagKind::Close | FlagKind::VarClose if !markup => push("closing flag without opening flag".to_string(), None, &flag.span),
+            _ => {}
+        }
+    }
+    for (open, reported) in stack {
+        if reported {
+            push("unclosed block".to_string(), None, &open.span);
+        }
+    }
+    problems.sort_by_key(|problem| problem.span.start);
+    problems
+}
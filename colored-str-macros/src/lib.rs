@@ -0,0 +1,608 @@
+// Copyright (C) 2023 Sebastien Guerri
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+#![warn(missing_docs)]
+
+//! Procedural macros for the [colored-str](https://crates.io/crates/colored-str) crate
+//!
+//! These macros are used by the `colored-str` macros and are not meant to be used directly.
+
+mod bake;
+mod check;
+
+use std::ops::Range;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2, TokenTree};
+use quote::{format_ident, quote, quote_spanned};
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Expr, Ident, LitStr, Token};
+
+/// An argument given after the format string
+enum Argument
+{
+    /// `expr` argument
+    Positional(Expr),
+    /// `name = expr` argument
+    Named(Ident, Expr),
+}
+
+impl Argument
+{
+    /// Expression of the argument
+    fn expr(&self) -> &Expr
+    {
+        match self {
+            Argument::Positional(expr) | Argument::Named(_, expr) => expr
+        }
+    }
+}
+
+impl Parse for Argument
+{
+    fn parse(input: ParseStream) -> syn::Result<Self>
+    {
+        if input.peek(Ident) && input.peek2(Token![=]) && !input.peek2(Token![==]) {
+            let name: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            return Ok(Argument::Named(name, input.parse()?));
+        }
+        Ok(Argument::Positional(input.parse()?))
+    }
+}
+
+/// Path to the crate, format string and arguments: `$crate, "template", arguments...`
+struct Input
+{
+    /// Path to the `colored-str` crate
+    krate: TokenTree,
+    /// Format string
+    template: LitStr,
+    /// Arguments given after the format string
+    arguments: Vec<Argument>,
+}
+
+impl Parse for Input
+{
+    fn parse(input: ParseStream) -> syn::Result<Self>
+    {
+        let krate: TokenTree = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let template: LitStr = input.parse()?;
+        let mut arguments = vec![];
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            arguments.push(input.parse()?);
+        }
+        Ok(Input { krate, template, arguments })
+    }
+}
+
+/// Reference to an argument within the format string
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ArgRef
+{
+    /// Positional argument
+    Index(usize),
+    /// Named argument or captured variable
+    Name(String),
+}
+
+/// Width or precision of a placeholder
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Count
+{
+    /// Written value
+    Literal(String),
+    /// Value given by an argument
+    Arg(ArgRef),
+}
+
+/// A `{...}` placeholder within the format string
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Placeholder
+{
+    /// Argument to format
+    value: ArgRef,
+    /// Fill, alignment, sign, `#` and `0` flags
+    flags: String,
+    /// Width, if any
+    width: Option<Count>,
+    /// Precision, if any
+    precision: Option<Count>,
+    /// Formatting trait
+    kind: String,
+}
+
+/// A piece of the format string
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece
+{
+    /// Literal text, which is markup
+    Literal(String),
+    /// Argument to format
+    Placeholder(Placeholder),
+}
+
+/// Formatting traits that can be used in a placeholder
+const KINDS: [&str; 12] = ["", "?", "x?", "X?", "x", "X", "o", "b", "e", "E", "p", "markup"];
+
+/// Read an argument reference (index or name) at the start of given text, returns it and its length
+fn read_arg(text: &str) -> Option<(ArgRef, usize)>
+{
+    if text.starts_with(|c: char| c.is_ascii_digit()) {
+        let len = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+        return Some((ArgRef::Index(text[..len].parse().ok()?), len));
+    }
+    let len = text.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(text.len());
+    let name = &text[..len];
+    if name.is_empty() {
+        None
+    } else if name.starts_with(|c: char| c.is_alphabetic() || c == '_') && name != "_" {
+        Some((ArgRef::Name(name.to_string()), len))
+    } else {
+        None
+    }
+}
+
+/// Read a width or precision at the start of given text, returns it and its length
+fn read_count(text: &str) -> Option<(Count, usize)>
+{
+    let (arg, len) = read_arg(text)?;
+    if text[len..].starts_with('$') {
+        return Some((Count::Arg(arg), len + 1));
+    }
+    match arg {
+        ArgRef::Index(_) => Some((Count::Literal(text[..len].to_string()), len)),
+        ArgRef::Name(_) => None
+    }
+}
+
+/// Parse the content of a placeholder, `next` being the next implicit positional argument
+fn parse_placeholder(content: &str, next: &mut usize) -> Result<Placeholder, String>
+{
+    let (arg, spec) = content.split_once(':').unwrap_or((content, ""));
+    let value = match arg {
+        "" => None,
+        _ => match read_arg(arg) {
+            Some((value, len)) if len == arg.len() => Some(value),
+            _ => return Err(format!("invalid argument name `{}`", arg))
+        }
+    };
+
+    let mut flags = String::new();
+    let mut rest = spec;
+
+    let mut chars = rest.chars();
+    match (chars.next(), chars.next()) {
+        (Some(fill), Some(align)) if "<^>".contains(align) => {
+            flags.push(fill);
+            flags.push(align);
+            rest = &rest[fill.len_utf8() + 1..];
+        },
+        (Some(align), _) if "<^>".contains(align) => {
+            flags.push(align);
+            rest = &rest[1..];
+        },
+        _ => {}
+    }
+    for flag in ['+', '-', '#'] {
+        if let Some(after) = rest.strip_prefix(flag) {
+            flags.push(flag);
+            rest = after;
+        }
+    }
+    if rest.starts_with('0') && !rest[1..].starts_with('$') {
+        flags.push('0');
+        rest = &rest[1..];
+    }
+
+    let width = match read_count(rest) {
+        Some((width, len)) => {
+            rest = &rest[len..];
+            Some(width)
+        },
+        None => None
+    };
+
+    let precision = match rest.strip_prefix('.') {
+        Some(after) if after.starts_with('*') => {
+            rest = &after[1..];
+            *next += 1;
+            Some(Count::Arg(ArgRef::Index(*next - 1)))
+        },
+        Some(after) => match read_count(after) {
+            Some((precision, len)) => {
+                rest = &after[len..];
+                Some(precision)
+            },
+            None => return Err(format!("invalid precision in `{{{}}}`", content))
+        },
+        None => None
+    };
+
+    if !KINDS.contains(&rest) {
+        return Err(format!("unknown format trait `{}`", rest));
+    }
+
+    let value = match value {
+        Some(value) => value,
+        None => {
+            *next += 1;
+            ArgRef::Index(*next - 1)
+        }
+    };
+
+    Ok(Placeholder { value, flags, width, precision, kind: rest.to_string() })
+}
+
+/// Split a format string into literal pieces and placeholders
+fn parse_template(template: &str) -> Result<Vec<Piece>, String>
+{
+    let mut pieces = vec![];
+    let mut literal = String::new();
+    let mut next = 0;
+    let mut chars = template.char_indices().peekable();
+
+    while let Some((id, c)) = chars.next() {
+        match c {
+            '{' if chars.peek().map(|&(_, c)| c) == Some('{') => {
+                chars.next();
+                literal.push('{');
+            },
+            '}' if chars.peek().map(|&(_, c)| c) == Some('}') => {
+                chars.next();
+                literal.push('}');
+            },
+            '{' => {
+                let end = match template[id..].find('}') {
+                    Some(end) => id + end,
+                    None => return Err("expected `}` but string was terminated".to_string())
+                };
+                if !literal.is_empty() {
+                    pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                }
+                let placeholder = parse_placeholder(&template[id + 1..end], &mut next)?;
+                pieces.push(Piece::Placeholder(placeholder));
+                while chars.peek().is_some_and(|&(id, _)| id <= end) {
+                    chars.next();
+                }
+            },
+            '}' => return Err("unmatched `}` found".to_string()),
+            _ => literal.push(c)
+        }
+    }
+
+    if !literal.is_empty() {
+        pieces.push(Piece::Literal(literal));
+    }
+    Ok(pieces)
+}
+
+/// Returns the span of a byte range of the value of a string literal, or of the whole literal
+///
+/// Precise spans are only available with a nightly compiler, and for literals without escapes.
+fn subspan(literal: &LitStr, range: Range<usize>) -> Span
+{
+    let token = literal.token();
+    let source = token.to_string();
+    let start = match source.find('"') {
+        Some(quote) if source.starts_with('r') => quote + 1,
+        Some(0) if !source.contains('\\') => 1,
+        _ => return literal.span()
+    };
+    token.subspan(start + range.start..start + range.end).unwrap_or_else(|| literal.span())
+}
+
+/// Returns the markup errors of a format string, combined into a single error
+fn check_markup(template: &LitStr) -> syn::Result<()>
+{
+    let value = template.value();
+    let errors = check::check(&value).into_iter().map(|problem| {
+        let line = value[..problem.span.start].matches('\n').count() + 1;
+        let start = value[..problem.span.start].rfind('\n').map_or(0, |id| id + 1);
+        let column = value[start..problem.span.start].chars().count() + 1;
+        let mut message = format!("invalid markup: {} at line {}, column {}", problem.message, line, column);
+        if let Some(help) = problem.help {
+            message = format!("{}, {}", message, help);
+        }
+        syn::Error::new(subspan(template, problem.span), message)
+    });
+    match errors.reduce(|mut errors, error| { errors.combine(error); errors }) {
+        Some(errors) => Err(errors),
+        None => Ok(())
+    }
+}
+
+/// Placeholders of a format string, ready to be formatted
+struct Formatted
+{
+    /// Pieces of the format string
+    pieces: Vec<Piece>,
+    /// `format_args!` expression of each placeholder, in order
+    values: Vec<TokenStream2>,
+    /// Binding of each argument
+    bindings: Vec<Ident>,
+}
+
+/// Check a format string and its arguments, and prepare the expressions formatting its placeholders
+fn prepare(input: &Input) -> syn::Result<Formatted>
+{
+    let Input { template, arguments, .. } = input;
+    let span = template.span();
+    let error = |message: String| syn::Error::new(span, format!("invalid format string: {}", message));
+
+    let pieces = parse_template(&template.value()).map_err(error)?;
+    check_markup(template)?;
+
+    let positional = arguments.iter().take_while(|arg| matches!(arg, Argument::Positional(_))).count();
+    if let Some(arg) = arguments[positional..].iter().find(|arg| matches!(arg, Argument::Positional(_))) {
+        return Err(syn::Error::new(arg.expr().span(), "positional arguments cannot follow named arguments"));
+    }
+
+    let bindings: Vec<Ident> = (0..arguments.len()).map(|id| format_ident!("__arg{}", id)).collect();
+    let mut used = vec![false; arguments.len()];
+
+    // Returns the expression to use for a given argument reference
+    let mut resolve = |arg: &ArgRef| -> syn::Result<TokenStream2> {
+        let id = match arg {
+            ArgRef::Index(index) if *index < arguments.len() => *index,
+            ArgRef::Index(index) => {
+                return Err(error(format!("invalid reference to positional argument {} ({} given)", index, positional)));
+            },
+            ArgRef::Name(name) => {
+                let found = arguments.iter().position(|arg| matches!(arg, Argument::Named(n, _) if n == name));
+                match found {
+                    Some(id) => id,
+                    None => {
+                        let ident = Ident::new(name, span);
+                        return Ok(quote!(#ident));
+                    }
+                }
+            }
+        };
+        used[id] = true;
+        let binding = &bindings[id];
+        let expr_span = arguments[id].expr().span();
+        Ok(quote_spanned!(expr_span=> *#binding))
+    };
+
+    let mut values = vec![];
+    for piece in &pieces {
+        let Piece::Placeholder(placeholder) = piece else {
+            continue;
+        };
+        let mut spec = format!("{{__v:{}", placeholder.flags);
+        let mut named = vec![];
+        match &placeholder.width {
+            Some(Count::Literal(width)) => spec.push_str(width),
+            Some(Count::Arg(arg)) => {
+                let value = resolve(arg)?;
+                spec.push_str("__w$");
+                named.push(quote!(__w = #value));
+            },
+            None => {}
+        }
+        match &placeholder.precision {
+            Some(Count::Literal(precision)) => {
+                spec.push('.');
+                spec.push_str(precision);
+            },
+            Some(Count::Arg(arg)) => {
+                let value = resolve(arg)?;
+                spec.push_str(".__p$");
+                named.push(quote!(__p = #value));
+            },
+            None => {}
+        }
+        if placeholder.kind != "markup" {
+            spec.push_str(&placeholder.kind);
+        }
+        spec.push('}');
+        let value = resolve(&placeholder.value)?;
+        let spec = LitStr::new(&spec, Span::call_site());
+        values.push(quote!(::std::format_args!(#spec, __v = #value, #(#named),*)));
+    }
+
+    if let Some(id) = used.iter().position(|used| !used) {
+        let message = match &arguments[id] {
+            Argument::Positional(_) => "argument never used",
+            Argument::Named(_, _) => "named argument never used"
+        };
+        return Err(syn::Error::new(arguments[id].expr().span(), message));
+    }
+
+    Ok(Formatted { pieces, values, bindings })
+}
+
+/// Generate the code building the markup text of a format string, arguments being bound
+fn markup_code(krate: &TokenTree, formatted: &Formatted) -> TokenStream2
+{
+    let mut values = formatted.values.iter();
+    let statements = formatted.pieces.iter().map(|piece| match piece {
+        Piece::Literal(text) => quote!(__markup.push_str(#text);),
+        Piece::Placeholder(placeholder) => {
+            let function = match placeholder.kind.as_str() {
+                "markup" => quote!(push_markup),
+                _ => quote!(push_escaped)
+            };
+            let value = values.next().expect("each placeholder has a value");
+            quote!(#krate::__private::#function(&mut __markup, #value);)
+        }
+    });
+    quote! {
+        {
+            let mut __markup = ::std::string::String::new();
+            #(#statements)*
+            __markup
+        }
+    }
+}
+
+/// Generate the code binding the arguments around given code
+fn bind_arguments(input: &Input, formatted: &Formatted, code: TokenStream2) -> TokenStream2
+{
+    let exprs = input.arguments.iter().map(|arg| arg.expr());
+    let bindings = &formatted.bindings;
+    quote! {
+        match (#(&#exprs,)*) {
+            (#(#bindings,)*) => #code
+        }
+    }
+}
+
+/// Input of the `format_colored!` macro: `sink, stream, $crate, "template", arguments...`
+struct ColoredInput
+{
+    /// Formatting macro of the standard library the colored text is given to
+    sink: Ident,
+    /// Variant of the stream the colored text is written to
+    stream: Ident,
+    /// Path to the crate, format string and arguments
+    input: Input,
+}
+
+impl Parse for ColoredInput
+{
+    fn parse(input: ParseStream) -> syn::Result<Self>
+    {
+        let sink: Ident = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let stream: Ident = input.parse()?;
+        input.parse::<Token![,]>()?;
+        Ok(ColoredInput { sink, stream, input: input.parse()? })
+    }
+}
+
+/// Escape the braces of a literal text, to be part of a format string
+fn escape_braces(text: &str) -> String
+{
+    text.replace('{', "{{").replace('}', "}}")
+}
+
+/// Generate the baked segments, then the plain format string and its arguments
+fn baked_code(krate: &TokenTree, segments: &[bake::Segment], values: &[TokenStream2]) -> (Vec<TokenStream2>, String, Vec<TokenStream2>)
+{
+    let mut colored = vec![];
+    let (mut plain, mut plain_args) = (String::new(), vec![]);
+    let mut values = values.iter();
+
+    for segment in segments {
+        let (mut inner, mut inner_args) = (String::new(), vec![]);
+        for part in &segment.parts {
+            match part {
+                bake::Part::Text(text) => {
+                    inner.push_str(&escape_braces(text));
+                    plain.push_str(&escape_braces(text));
+                },
+                bake::Part::Placeholder => {
+                    let value = values.next().expect("each placeholder has a value");
+                    inner.push_str("{}");
+                    inner_args.push(value.clone());
+                    plain.push_str("{}");
+                    plain_args.push(value.clone());
+                }
+            }
+        }
+        let style = &segment.style;
+        let decorations = style.decoration_bits();
+        let (fgcolor, bgcolor) = (style.fg.as_deref().unwrap_or_default(), style.bg.as_deref().unwrap_or_default());
+        colored.push(quote!(#krate::__private::Segment::new(#decorations, #fgcolor, #bgcolor, ::std::format_args!(#inner, #(#inner_args),*))));
+    }
+    (colored, plain, plain_args)
+}
+
+/// Generate the code of the `format_colored!` macro
+fn expand_colored(colored: ColoredInput) -> syn::Result<TokenStream2>
+{
+    let ColoredInput { sink, stream, input } = colored;
+    let krate = &input.krate;
+    let formatted = prepare(&input)?;
+    let markup = markup_code(krate, &formatted);
+    let runtime = quote! {
+        {
+            let __markup = #markup;
+            ::std::#sink!("{}", #krate::__private::Runtime::new(&__markup, #krate::Stream::#stream))
+        }
+    };
+
+    // Each placeholder stands for a single character, so that the markup of the format string can be baked
+    let mut masked = String::new();
+    let mut bakeable = true;
+    for piece in &formatted.pieces {
+        match piece {
+            Piece::Literal(text) => {
+                bakeable &= !text.contains(check::PLACEHOLDER);
+                masked.push_str(text);
+            },
+            Piece::Placeholder(placeholder) => {
+                bakeable &= placeholder.kind != "markup";
+                masked.push(check::PLACEHOLDER);
+            }
+        }
+    }
+    let Some((segments, depth)) = bake::bake(&masked).filter(|_| bakeable) else {
+        return Ok(bind_arguments(&input, &formatted, runtime));
+    };
+
+    let (colored, plain, plain_args) = baked_code(krate, &segments, &formatted.values);
+    let depth = Ident::new(depth.name(), Span::call_site());
+    let code = quote! {
+        match #krate::__private::baked(#krate::Stream::#stream, #krate::ColorDepth::#depth) {
+            ::std::option::Option::Some(true) => ::std::#sink!("{}", #krate::__private::Baked::new(&[#(#colored),*])),
+            ::std::option::Option::Some(false) => ::std::#sink!(#plain, #(#plain_args),*),
+            ::std::option::Option::None => #runtime
+        }
+    };
+    Ok(bind_arguments(&input, &formatted, code))
+}
+
+/// Formats given arguments as per [`format!`] into a colored text, given to a formatting macro.
+///
+/// The first arguments are the formatting macro of the standard library to give the colored text
+/// to, such as `println`, the variant of the `Stream` it is written to, and the path to the
+/// `colored-str` crate. Then come the format string and its arguments. The format string is
+/// kept as is, so that its flags are parsed as markup, while the arguments are escaped to be
+/// displayed as is. An argument formatted with the `markup` formatting trait, such as
+/// `{name:markup}`, is kept as is.
+///
+/// The markup of the format string is checked: invalid hexadecimal colors, invalid color
+/// functions, flags that do not match, and unknown styles close to a built-in style, are
+/// compile errors. Other unknown styles may be defined by a theme at runtime.
+///
+/// When possible, the markup of the format string is rendered at compile time, and the formatting
+/// macro is given its texts with the codes of their styles baked in, only the changes of style
+/// between them being written at runtime, as `colored-str` does. This is the case when its
+/// styles are built-in terminal colors, decorations, removals, `#RRGGBB` or `#RGB` colors, and
+/// `cN` colors, and when no argument is formatted with the `markup` formatting trait. The baked
+/// format string is only used at runtime if no theme is set and if the color depth is enough,
+/// otherwise the markup text is rendered at runtime.
+#[proc_macro]
+pub fn format_colored(input: TokenStream) -> TokenStream
+{
+    let input = parse_macro_input!(input as ColoredInput);
+    match expand_colored(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => {
+            // Within a block, so that several errors can be reported in expression position
+            let errors = error.to_compile_error();
+            quote!({ #errors ::std::unreachable!() }).into()
+        }
+    }
+}
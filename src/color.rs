@@ -0,0 +1,272 @@
+// Copyright (C) 2023 Sebastien Guerri
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Color parsing and conversion
+
+use std::ops::RangeInclusive;
+use std::sync::RwLock;
+
+use colored_str_syntax::CSS_COLORS;
+use lazy_static::lazy_static;
+
+/// Background against which colors with an alpha component are blended
+static ALPHA_BACKGROUND: RwLock<Option<(u8, u8, u8)>> = RwLock::new(None);
+
+/// Sets the background against which colors with an alpha component are blended.
+///
+/// Colors such as `#RRGGBBAA` are blended against this background. With `None`,
+/// which is the default, the alpha component is ignored.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{colored, set_alpha_background};
+///
+/// set_alpha_background(Some((255, 255, 255)));
+/// println!("{}", colored("<#FF000080>this is light red</>"));
+/// # set_alpha_background(None);
+/// ```
+pub fn set_alpha_background(color: Option<(u8, u8, u8)>)
+{
+    *ALPHA_BACKGROUND.write().unwrap_or_else(|e| e.into_inner()) = color;
+}
+
+/// Returns the background against which colors with an alpha component are blended
+pub fn alpha_background() -> Option<(u8, u8, u8)>
+{
+    *ALPHA_BACKGROUND.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Blend a color with given opacity against the alpha background, if any
+fn blend((r, g, b): (u8, u8, u8), alpha: u8) -> (u8, u8, u8)
+{
+    let Some((br, bg, bb)) = alpha_background() else {
+        return (r, g, b);
+    };
+    let mix = |fore: u8, back: u8| -> u8 {
+        ((fore as u32 * alpha as u32 + back as u32 * (255 - alpha as u32) + 127) / 255) as u8
+    };
+    (mix(r, br), mix(g, bg), mix(b, bb))
+}
+
+/// Parse `RGB`, `RGBA`, `RRGGBB` or `RRGGBBAA` hexadecimal digits, case insensitive
+pub(crate) fn parse_hex(hex: &str) -> Option<(u8, u8, u8)>
+{
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digits: Vec<u8> = match hex.len() {
+        3 | 4 => hex.chars().map(|c| c.to_digit(16).map(|d| d as u8 * 17)).collect::<Option<_>>()?,
+        6 | 8 => (0..hex.len()).step_by(2).map(|id| u8::from_str_radix(&hex[id..id + 2], 16).ok()).collect::<Option<_>>()?,
+        _ => return None
+    };
+    let color = (digits[0], digits[1], digits[2]);
+    match digits.get(3) {
+        Some(&alpha) => Some(blend(color, alpha)),
+        None => Some(color)
+    }
+}
+
+/// Names of the CSS named colors
+pub(crate) fn css_names() -> impl Iterator<Item = &'static str>
+{
+    CSS_COLORS.iter().map(|(name, _)| *name)
+}
+
+/// Returns the components of a CSS named color, case insensitive
+pub(crate) fn css_color(name: &str) -> Option<(u8, u8, u8)>
+{
+    let name = name.to_lowercase();
+    CSS_COLORS.binary_search_by_key(&name.as_str(), |(name, _)| name).ok().map(|id| CSS_COLORS[id].1)
+}
+
+/// Removes the case insensitive `on_` prefix of a background style, if any
+pub(crate) fn strip_on(style: &str) -> Option<&str>
+{
+    style.get(..3).filter(|prefix| prefix.eq_ignore_ascii_case("on_"))?;
+    Some(&style[3..])
+}
+
+/// Parse a `cN` or `ansi(N)` 256 colors palette index, case insensitive
+pub(crate) fn parse_ansi(text: &str) -> Option<u8>
+{
+    let lowercase = text.to_lowercase();
+    let index = match lowercase.strip_prefix("ansi(") {
+        Some(args) => args.strip_suffix(')')?.trim(),
+        None => lowercase.strip_prefix('c')?
+    };
+    if index.is_empty() || index.len() > 3 || !index.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    index.parse().ok()
+}
+
+/// Parse a number, a percentage being relative to given value
+fn parse_number(text: &str, percent: f64) -> Option<f64>
+{
+    let number = match text.strip_suffix('%') {
+        Some(number) => number.parse::<f64>().ok()? * percent / 100.0,
+        None => text.parse::<f64>().ok()?
+    };
+    Some(number).filter(|number| number.is_finite())
+}
+
+/// Clamp a value within given range, recording whether it was out of range
+fn clamp(value: f64, min: f64, max: f64, clamped: &mut bool) -> f64
+{
+    if value < min || value > max {
+        *clamped = true;
+    }
+    value.clamp(min, max)
+}
+
+/// Convert HSL components, hue in degrees and others within 0 and 1, to RGB components within 0 and 1
+fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> (f64, f64, f64)
+{
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let component = |n: f64| -> f64 {
+        let k = (n + hue / 30.0).rem_euclid(12.0);
+        lightness - chroma / 2.0 * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+    (component(0.0), component(8.0), component(4.0))
+}
+
+/// Convert OKLCH components, hue in degrees, to linear sRGB components
+fn oklch_to_linear(lightness: f64, chroma: f64, hue: f64) -> (f64, f64, f64)
+{
+    let (a, b) = (chroma * hue.to_radians().cos(), chroma * hue.to_radians().sin());
+    let l = (lightness + 0.396_337_777_4 * a + 0.215_803_757_3 * b).powi(3);
+    let m = (lightness - 0.105_561_345_8 * a - 0.063_854_172_8 * b).powi(3);
+    let s = (lightness - 0.089_484_177_5 * a - 1.291_485_548_0 * b).powi(3);
+    (
+        4.076_741_662_1 * l - 3.307_711_591_3 * m + 0.230_969_929_2 * s,
+        -1.268_438_004_6 * l + 2.609_757_401_1 * m - 0.341_319_396_5 * s,
+        -0.004_196_086_3 * l - 0.703_418_614_7 * m + 1.707_614_701_0 * s,
+    )
+}
+
+/// Apply the sRGB transfer function to a linear component
+fn gamma(value: f64) -> f64
+{
+    match value <= 0.003_130_8 {
+        true => 12.92 * value,
+        false => 1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Parse a `rgb()`, `hsl()` or `oklch()` color function, case insensitive
+///
+/// Returns its RGB components and whether they were clamped to fit, components
+/// being separated by commas or spaces.
+pub(crate) fn parse_function(text: &str) -> Option<((u8, u8, u8), bool)>
+{
+    let (name, args) = text.split_once('(')?;
+    let args: Vec<&str> = args.strip_suffix(')')?.split([',', ' ']).filter(|arg| !arg.is_empty()).collect();
+    let [x, y, z] = args[..] else {
+        return None;
+    };
+
+    let mut clamped = false;
+    let (r, g, b) = match name.to_lowercase().as_str() {
+        "rgb" => {
+            let mut component = |text: &str| parse_number(text, 255.0).map(|value| clamp(value, 0.0, 255.0, &mut clamped) / 255.0);
+            (component(x)?, component(y)?, component(z)?)
+        },
+        "hsl" => {
+            let hue = parse_number(x, f64::NAN)?;
+            let saturation = clamp(parse_number(y, 100.0)?, 0.0, 100.0, &mut clamped) / 100.0;
+            let lightness = clamp(parse_number(z, 100.0)?, 0.0, 100.0, &mut clamped) / 100.0;
+            hsl_to_rgb(hue, saturation, lightness)
+        },
+        "oklch" => {
+            let lightness = clamp(parse_number(x, 1.0)?, 0.0, 1.0, &mut clamped);
+            let chroma = clamp(parse_number(y, 0.4)?, 0.0, f64::INFINITY, &mut clamped);
+            let hue = parse_number(z, f64::NAN)?;
+            let (r, g, b) = oklch_to_linear(lightness, chroma, hue);
+            // Tolerate rounding errors of the conversion
+            let mut component = |value: f64| {
+                let value = match (-1e-4..=1.0 + 1e-4).contains(&value) {
+                    true => value.clamp(0.0, 1.0),
+                    false => clamp(value, 0.0, 1.0, &mut clamped)
+                };
+                gamma(value)
+            };
+            (component(r), component(g), component(b))
+        },
+        _ => return None
+    };
+    let byte = |value: f64| (value * 255.0).round().clamp(0.0, 255.0) as u8;
+    Some(((byte(r), byte(g), byte(b)), clamped))
+}
+
+/// Components of the 16 first colors of the palette, as displayed by xterm
+const ANSI_16: [(u8, u8, u8); 16] = [
+    (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0), (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+    (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0), (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+];
+
+/// Returns the components of a color of the 256 colors palette
+pub(crate) fn ansi_components(index: u8) -> (u8, u8, u8)
+{
+    let level = |value: u8| if value == 0 { 0 } else { 55 + value * 40 };
+    match index {
+        0..=15 => ANSI_16[index as usize],
+        16..=231 => {
+            let index = index - 16;
+            (level(index / 36), level(index / 6 % 6), level(index % 6))
+        },
+        _ => {
+            let gray = 8 + (index - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+/// Convert sRGB components to OKLab components
+fn to_oklab((r, g, b): (u8, u8, u8)) -> (f64, f64, f64)
+{
+    let linear = |value: u8| {
+        let value = value as f64 / 255.0;
+        match value <= 0.040_45 {
+            true => value / 12.92,
+            false => ((value + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (linear(r), linear(g), linear(b));
+    let l = (0.412_221_470_8 * r + 0.536_332_536_3 * g + 0.051_445_992_9 * b).cbrt();
+    let m = (0.211_903_498_2 * r + 0.680_699_545_1 * g + 0.107_396_956_6 * b).cbrt();
+    let s = (0.088_302_461_9 * r + 0.281_718_837_6 * g + 0.629_978_700_5 * b).cbrt();
+    (
+        0.210_454_255_3 * l + 0.793_617_785_0 * m - 0.004_072_046_8 * s,
+        1.977_998_495_1 * l - 2.428_592_205_0 * m + 0.450_593_709_9 * s,
+        0.025_904_037_1 * l + 0.782_771_766_2 * m - 0.808_675_766_0 * s,
+    )
+}
+
+/// Returns the index of the color of given palette range perceptually closest to given components
+pub(crate) fn closest_ansi(rgb: (u8, u8, u8), range: RangeInclusive<u8>) -> u8
+{
+    lazy_static! {
+        static ref PALETTE: Vec<(f64, f64, f64)> = (0..=255).map(|index| to_oklab(ansi_components(index))).collect();
+    }
+    let (l, a, b) = to_oklab(rgb);
+    let distance = |index: &u8| {
+        let (pl, pa, pb) = PALETTE[*index as usize];
+        (l - pl).powi(2) + (a - pa).powi(2) + (b - pb).powi(2)
+    };
+    range.min_by(|x, y| distance(x).total_cmp(&distance(y))).expect("palette range is not empty")
+}
@@ -0,0 +1,283 @@
+// Copyright (C) 2023 Sebastien Guerri
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Lint of markup texts
+
+use std::fmt;
+use std::ops::Range;
+
+use colored::{ColoredString, Style};
+use colored_str_syntax::{distance, STYLE_NAMES};
+
+use crate::color;
+use crate::error::{line_column, style_error, unmatched_error, MarkupErrorKind};
+use crate::markup::{match_flags, tokenize, TokenKind};
+use crate::theme::{self, Theme};
+
+/// Severity of a [`Diagnostic`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity
+{
+    /// The text is not displayed as intended
+    Error,
+    /// The text is displayed as intended, but could be simplified
+    Warning,
+}
+
+/// Kind of a [`Diagnostic`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DiagnosticKind
+{
+    /// An error, as reported by [`try_colored()`](crate::try_colored())
+    Error(MarkupErrorKind),
+    /// A style has no effect because a later style of the same flag overrides it
+    OverriddenStyle(String),
+    /// A block or a variation has no content
+    EmptyBlock,
+}
+
+/// A problem found in a markup text, as returned by [`check()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic
+{
+    /// Severity of the problem
+    pub severity: Severity,
+    /// Kind of the problem
+    pub kind: DiagnosticKind,
+    /// Description of the problem
+    pub message: String,
+    /// Suggested fix, if any
+    pub help: Option<String>,
+    /// Byte range of the offending text
+    pub span: Range<usize>,
+    /// Line of the offending text, starting at 1
+    pub line: usize,
+    /// Column of the offending text in characters, starting at 1
+    pub column: usize,
+}
+
+impl fmt::Display for Severity
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{}: {} at line {}, column {}", self.severity, self.message, self.line, self.column)
+    }
+}
+
+/// Part of the style a single style applies to, so that two styles of the same part override each other
+#[derive(Debug, Clone, PartialEq)]
+enum StylePart
+{
+    /// Foreground color
+    Foreground,
+    /// Background color
+    Background,
+    /// A decoration
+    Decoration(Style),
+}
+
+/// Returns the part of the style a known single built-in style applies to
+fn style_part(style: &str, theme: &Theme) -> Option<StylePart>
+{
+    if theme.get(style).is_some() {
+        return None;
+    }
+    let lowercase = style.to_lowercase();
+    match lowercase.as_str() {
+        "default" => return Some(StylePart::Foreground),
+        "on_default" => return Some(StylePart::Background),
+        _ => {}
+    }
+    let name = style.strip_prefix('!').unwrap_or(style);
+    let result = crate::test_builtin(name)?(ColoredString::default());
+    if result.fgcolor.is_some() {
+        Some(StylePart::Foreground)
+    } else if result.bgcolor.is_some() {
+        Some(StylePart::Background)
+    } else {
+        Some(StylePart::Decoration(result.style))
+    }
+}
+
+/// Returns the known style closest to an unknown style, if close enough
+fn suggest(style: &str, theme: &Theme) -> Option<String>
+{
+    let lowercase = style.to_lowercase();
+    let max = (lowercase.chars().count() / 3).max(1);
+    let css = color::css_names().flat_map(|name| [name.to_string(), format!("on_{}", name)]);
+    let mut names: Vec<String> = theme.names().map(|name| name.to_string()).collect();
+    names.sort();
+    names.into_iter().chain(STYLE_NAMES.iter().map(|name| name.to_string())).chain(css)
+        .map(|name| (distance(&lowercase, &name), name))
+        .filter(|(distance, _)| *distance <= max)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name)
+}
+
+/// Returns the components a color function is clamped to
+fn clamped(style: &str) -> Option<(u8, u8, u8)>
+{
+    color::parse_function(color::strip_on(style).unwrap_or(style)).map(|(rgb, _)| rgb)
+}
+
+/// Checks given text, returning all problems found, in order of appearance.
+///
+/// Reports the same errors as [`try_colored()`](crate::try_colored()), with suggestions
+/// for unknown styles, and warns about styles overridden within the same flag and
+/// about empty blocks. Use [`report()`] to display them.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{check, Severity};
+///
+/// let diagnostics = check("<rde>this is red</><blue+red>this is red too</>");
+/// assert_eq!(diagnostics.len(), 2);
+/// assert_eq!(diagnostics[0].severity, Severity::Error);
+/// assert_eq!(diagnostics[0].help.as_deref(), Some("did you mean `red`?"));
+/// assert_eq!(diagnostics[1].severity, Severity::Warning);
+/// ```
+pub fn check(text: &str) -> Vec<Diagnostic>
+{
+    let tokens = tokenize(text);
+    let matched = match_flags(&tokens);
+    let theme = theme::current_theme();
+
+    let mut diagnostics = vec![];
+    let mut push = |severity: Severity, kind: DiagnosticKind, help: Option<String>, span: Range<usize>| {
+        let (line, column) = line_column(text, span.start);
+        let message = match &kind {
+            DiagnosticKind::Error(error) => error.to_string(),
+            DiagnosticKind::OverriddenStyle(style) => format!("style `{}` is overridden", style),
+            DiagnosticKind::EmptyBlock => "empty block".to_string()
+        };
+        diagnostics.push(Diagnostic { severity, kind, message, help, span, line, column });
+    };
+
+    let mut depth = 0;
+    for (id, (token, &is_matched)) in tokens.iter().zip(&matched).enumerate() {
+        if let Some(error) = unmatched_error(token.kind, depth).filter(|_| !is_matched) {
+            let help = match token.kind {
+                TokenKind::Open | TokenKind::VarOpen => Some(format!("add a closing flag or escape it as `\\{}`", token.source)),
+                _ => Some(format!("remove it or escape it as `\\{}`", token.source))
+            };
+            push(Severity::Error, DiagnosticKind::Error(error), help, token.span.clone());
+            continue;
+        }
+
+        match token.kind {
+            TokenKind::Open | TokenKind::VarOpen => {
+                if token.kind == TokenKind::Open {
+                    depth += 1;
+                }
+
+                // Check each style of the flag
+                let mut start = token.span.end - token.styles.len() - 1;
+                let mut parts: Vec<(Range<usize>, &str, StylePart)> = vec![];
+                for style in token.styles.split('+') {
+                    let span = start..start + style.len();
+                    start += style.len() + 1;
+                    if let Some(error) = style_error(style.trim(), &theme) {
+                        let help = match error {
+                            MarkupErrorKind::OutOfGamut(_) => clamped(style.trim()).map(|(r, g, b)| format!("clamped to `#{:02X}{:02X}{:02X}`", r, g, b)),
+                            _ => suggest(style.trim(), &theme).map(|name| format!("did you mean `{}`?", name))
+                        };
+                        push(Severity::Error, DiagnosticKind::Error(error), help, span);
+                    } else if let Some(part) = style_part(style.trim(), &theme) {
+                        parts.push((span, style, part));
+                    }
+                }
+                for (id, (span, style, part)) in parts.iter().enumerate() {
+                    if let Some((_, by, _)) = parts[id + 1..].iter().find(|(_, _, other)| other == part) {
+                        let help = Some(format!("remove `{}`, overridden by `{}`", style, by));
+                        push(Severity::Warning, DiagnosticKind::OverriddenStyle(style.to_string()), help, span.clone());
+                    }
+                }
+
+                // Check if the block is empty
+                let close = tokens.get(id + 1).map(|next| next.kind);
+                let expected = match token.kind {
+                    TokenKind::Open => TokenKind::Close,
+                    _ => TokenKind::VarClose
+                };
+                if close == Some(expected) && matched[id + 1] {
+                    let help = Some("remove the block".to_string());
+                    push(Severity::Warning, DiagnosticKind::EmptyBlock, help, token.span.start..tokens[id + 1].span.end);
+                }
+            },
+            TokenKind::Close => depth -= 1,
+            _ => {}
+        }
+    }
+
+    diagnostics
+}
+
+/// Renders given diagnostics for humans, underlining the offending text of given source.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{check, report};
+///
+/// let text = "<rde>this is red</>";
+/// assert_eq!(report(text, &check(text)), "\
+/// error: unknown style `rde`
+///  --> line 1, column 2
+///   |
+/// 1 | <rde>this is red</>
+///   |  ^^^ did you mean `red`?
+/// ");
+/// ```
+pub fn report(source: &str, diagnostics: &[Diagnostic]) -> String
+{
+    let mut result = String::new();
+    for diagnostic in diagnostics {
+        let start = source[..diagnostic.span.start].rfind('\n').map_or(0, |id| id + 1);
+        let end = source[diagnostic.span.start..].find('\n').map_or(source.len(), |id| diagnostic.span.start + id);
+        let line = &source[start..end];
+        let number = diagnostic.line.to_string();
+        let margin = " ".repeat(number.len());
+        let carets = "^".repeat(source[diagnostic.span.start..diagnostic.span.end.min(end)].chars().count().max(1));
+
+        result.push_str(&format!("{}: {}\n", diagnostic.severity, diagnostic.message));
+        result.push_str(&format!("{}--> line {}, column {}\n", margin, diagnostic.line, diagnostic.column));
+        result.push_str(&format!("{} |\n", margin));
+        result.push_str(&format!("{} | {}\n", number, line));
+        result.push_str(&format!("{} | {}{}", margin, " ".repeat(diagnostic.column - 1), carets));
+        if let Some(help) = &diagnostic.help {
+            result.push(' ');
+            result.push_str(help);
+        }
+        result.push('\n');
+    }
+    result
+}
@@ -0,0 +1,163 @@
+// Copyright (C) 2023 Sebastien Guerri
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Errors found while strictly parsing a markup text
+
+use std::error::Error;
+use std::fmt;
+
+use crate::color;
+use crate::theme::{self, Theme};
+use crate::markup::{match_flags, tokenize, TokenKind};
+
+/// Kind of a [`MarkupError`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MarkupErrorKind
+{
+    /// A style of an opening flag is unknown
+    UnknownStyle(String),
+    /// A style of an opening flag is an invalid hexadecimal color
+    BadHex(String),
+    /// A style of an opening flag is an invalid color function
+    BadFunction(String),
+    /// A color function of an opening flag is out of gamut, and clamped to fit
+    OutOfGamut(String),
+    /// An opening flag has no matching closing flag
+    UnclosedBlock,
+    /// A closing flag has no matching opening flag
+    StrayCloser,
+    /// A variation is not within a block
+    VariationOutsideBlock,
+    /// A placeholder of a [`Template`](crate::Template) is invalid or has a format spec
+    BadPlaceholder(String),
+}
+
+/// An error found while strictly parsing a markup text, as returned by [`try_colored()`](crate::try_colored())
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkupError
+{
+    /// Kind of the error
+    pub kind: MarkupErrorKind,
+    /// Offending flag
+    pub tag: String,
+    /// Byte offset of the offending flag in the parsed text
+    pub offset: usize,
+    /// Line of the offending flag, starting at 1
+    pub line: usize,
+    /// Column of the offending flag in characters, starting at 1
+    pub column: usize,
+}
+
+impl fmt::Display for MarkupErrorKind
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self {
+            MarkupErrorKind::UnknownStyle(style) => write!(f, "unknown style `{}`", style),
+            MarkupErrorKind::BadHex(style) => write!(f, "invalid hexadecimal color `{}`", style),
+            MarkupErrorKind::BadFunction(style) => write!(f, "invalid color function `{}`", style),
+            MarkupErrorKind::OutOfGamut(style) => write!(f, "out of gamut color `{}`", style),
+            MarkupErrorKind::UnclosedBlock => f.write_str("unclosed block"),
+            MarkupErrorKind::StrayCloser => f.write_str("closing flag without opening flag"),
+            MarkupErrorKind::VariationOutsideBlock => f.write_str("variation outside of a block"),
+            MarkupErrorKind::BadPlaceholder(placeholder) => write!(f, "invalid placeholder `{}`", placeholder),
+        }
+    }
+}
+
+impl fmt::Display for MarkupError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{} in `{}` at line {}, column {}", self.kind, self.tag, self.line, self.column)
+    }
+}
+
+impl Error for MarkupError {}
+
+/// Returns the line and column of a byte offset in given text, both starting at 1
+pub(crate) fn line_column(text: &str, offset: usize) -> (usize, usize)
+{
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let start = before.rfind('\n').map_or(0, |id| id + 1);
+    (line, before[start..].chars().count() + 1)
+}
+
+/// Returns the error kind of a single style, if any
+pub(crate) fn style_error(style: &str, theme: &Theme) -> Option<MarkupErrorKind>
+{
+    let name = color::strip_on(style).unwrap_or(style);
+    if crate::test_style(style, theme).is_some() {
+        return match color::parse_function(name) {
+            Some((_, true)) => Some(MarkupErrorKind::OutOfGamut(style.to_string())),
+            _ => None
+        };
+    }
+    if name.starts_with('#') {
+        Some(MarkupErrorKind::BadHex(style.to_string()))
+    } else if name.contains('(') {
+        Some(MarkupErrorKind::BadFunction(style.to_string()))
+    } else {
+        Some(MarkupErrorKind::UnknownStyle(style.to_string()))
+    }
+}
+
+/// Returns the error kind of a flag that does not match, `depth` being the number of blocks it is in
+pub(crate) fn unmatched_error(kind: TokenKind, depth: usize) -> Option<MarkupErrorKind>
+{
+    match kind {
+        TokenKind::Text => None,
+        TokenKind::Open => Some(MarkupErrorKind::UnclosedBlock),
+        TokenKind::VarOpen if depth == 0 => Some(MarkupErrorKind::VariationOutsideBlock),
+        TokenKind::VarOpen => Some(MarkupErrorKind::UnclosedBlock),
+        TokenKind::Close | TokenKind::VarClose => Some(MarkupErrorKind::StrayCloser)
+    }
+}
+
+/// Returns all errors found in given text, in order of appearance
+pub(crate) fn markup_errors(text: &str) -> Vec<MarkupError>
+{
+    let tokens = tokenize(text);
+    let matched = match_flags(&tokens);
+    let theme = theme::current_theme();
+
+    let mut errors = vec![];
+    let mut depth = 0;
+
+    for (token, matched) in tokens.iter().zip(matched) {
+        let kinds: Vec<MarkupErrorKind> = match (token.kind, matched) {
+            (TokenKind::Text, _) => vec![],
+            (TokenKind::Open, true) | (TokenKind::VarOpen, true) => {
+                if token.kind == TokenKind::Open {
+                    depth += 1;
+                }
+                token.styles.split('+').filter_map(|style| style_error(style.trim(), &theme)).collect()
+            },
+            (TokenKind::Close, true) => {
+                depth -= 1;
+                vec![]
+            },
+            (TokenKind::VarClose, true) => vec![],
+            (kind, false) => unmatched_error(kind, depth).into_iter().collect()
+        };
+        for kind in kinds {
+            let (line, column) = line_column(text, token.span.start);
+            errors.push(MarkupError { kind, tag: token.source.to_string(), offset: token.span.start, line, column });
+        }
+    }
+    errors
+}
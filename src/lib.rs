@@ -31,23 +31,30 @@
 //! colored-str = "0.1.8"
 //! ```
 //! 
+//! Enable the `toml` or `json` features to load themes from style sheets.
+//! 
+//! ```toml
+//! [dependencies]
+//! colored-str = { version = "0.1.8", features = ["toml"] }
+//! ```
+//! 
 //! ## How to use
 //! 
 //! Styles must be written within `<...>` opening flag and `</>` closing flag.
 //! 
 //! Style variations must be written within `<+...>` opening flag and `<->` closing flag.
 //! 
-//! See below examples.
+//! Blocks and variations can be nested at any depth, each closing flag restores the style
+//! of the enclosing block. A block starts from an unstyled text, while a variation adjusts
+//! the style of its enclosing block. Flags that do not match are kept as text.
 //! 
-//! ## Limitations
+//! A `<` or a `\` can be escaped with a `\` to be displayed as is, so that `\<red>` is
+//! displayed `<red>`. Use [`escape()`] to make any text safe to embed.
 //! 
-//! **Blocks cannot be overlapped**  
-//! Such code `<red> ... <blue> ... </> ... </>` will not work properly.  
-//! This is true as well for variations : `<red><+blue> ... <+bold> ... <-><-></>` will not work properly.  
+//! Every `\\` is displayed `\`, even out of flags, so that `\\server\share` is displayed
+//! `\server\share`.
 //! 
-//! **A style cannot be removed**  
-//! With `<red+bold> ... here I want to keep red only => impossible </>`.  
-//! The workaround is as follows: `<red> <+bold> ... <-> here I have red only </>`
+//! See below examples.
 //! 
 //! ## Examples
 //! 
@@ -73,6 +80,27 @@
 //! coloredln!("<red>{}</>", message);
 //! ```
 //! 
+//! Variables are displayed as is, even if they contain flags. Use `{:markup}` to parse them.
+//! 
+//! ```
+//! use colored_str::coloredln;
+//! 
+//! let path = "<none>";
+//! coloredln!("<red>{path}</>");
+//! let message = "<bold>this is bold</>";
+//! coloredln!("<red>{message:markup}</>");
+//! ```
+//! 
+//! Variables already colored, such as a `ColoredString`, keep their styles: the style of the
+//! enclosing block is written again after them, even after a partial reset such as `\x1B[39m`.
+//! 
+//! ```
+//! use colored_str::{colored, coloredln};
+//! 
+//! let name = colored("<blue>main.rs</>");
+//! coloredln!("<red>error in {name}, see {name} for details</>");
+//! ```
+//! 
 //! You can add styles adjustments in a block
 //! 
 //! ```
@@ -82,6 +110,24 @@
 //! coloredln!("<red>this is red <+bold+on_blue>this is red on blue and bold<-> then red again </>");
 //! ```
 //! 
+//! You can remove styles in a block
+//! 
+//! ```
+//! use colored_str::coloredln;
+//! 
+//! coloredln!("<red+bold>this is red and bold <+!bold>this is red only<-> then red and bold again </>");
+//! coloredln!("<red+on_blue>this is red on blue <+default>this is on blue only<-> then red on blue again </>");
+//! ```
+//! 
+//! You can nest blocks and variations
+//! 
+//! ```
+//! use colored_str::coloredln;
+//! 
+//! coloredln!("<red>this is red <blue>this is blue</> then red again </>");
+//! coloredln!("<red>this is red <+bold>red and bold <+on_blue>red and bold on blue<-> red and bold<-> red</>");
+//! ```
+//! 
 //! You can also use it as a trait
 //! 
 //! ```
@@ -90,6 +136,159 @@
 //! println!("{}", s);
 //! ```
 //! 
+//! You can reject texts with unknown styles or flags that do not match
+//! 
+//! ```
+//! use colored_str::try_colored;
+//! 
+//! match try_colored("<rde>this is red</>") {
+//!     Ok(text) => println!("{}", text),
+//!     Err(error) => eprintln!("invalid template: {}", error)
+//! }
+//! ```
+//! 
+//! Texts given to the macros are checked at compile time: invalid hexadecimal colors, invalid
+//! color functions, flags that do not match, and unknown styles close to a built-in style,
+//! such as `<rde>`, are compile errors. Other unknown styles may be defined by a theme at runtime.
+//! Texts made of terminal colors, decorations, removals, `#RRGGBB`, `#RGB` and `cN` colors are
+//! also rendered at compile time, so that printing them only adds to `println!` a check of the
+//! settings, the environment and the terminal being read once. They are rendered at runtime if
+//! styles are set by a theme or by `COLORED_STR_THEME`, or if the color depth is not enough.
+//! 
+//! You can check a text to get all its problems at once, with suggestions
+//! 
+//! ```
+//! use colored_str::{check, report};
+//! 
+//! let text = "<rde>this is red</><blue+red>this is red too</>";
+//! eprint!("{}", report(text, &check(text)));
+//! ```
+//! 
+//! You can parse a text into a tree of nodes, to inspect or transform it before rendering
+//! 
+//! ```
+//! use colored_str::{parse, Colored, Node};
+//! 
+//! let mut markup = parse("<red>this is red</>");
+//! if let Node::Block(block) = &mut markup.nodes[0] {
+//!     block.styles = "blue".to_string();
+//! }
+//! println!("{}", markup.colored());
+//! ```
+//! 
+//! You can compile a text once to render it many times, styles being resolved when compiling.
+//! Placeholders are `{}`, `{0}` or `{name}`: format specs such as `{:>6}` are not accepted
+//! 
+//! ```
+//! use colored_str::{Args, Template};
+//! 
+//! let template = Template::compile("<red>{}</> lines in <bold>{file}</>").unwrap();
+//! for count in 0..3 {
+//!     println!("{}", template.render(Args::new().arg(&count).named("file", &"main.rs")));
+//! }
+//! ```
+//! 
+//! You can write a big text to any writer in a single pass, without intermediate strings
+//! 
+//! ```
+//! use std::fmt::Write;
+//! use colored_str::{write_colored, Stream};
+//! 
+//! let mut log = String::with_capacity(4096);
+//! for id in 0..100 {
+//!     write_colored(&mut log, &format!("<dimmed>{:03}</> <green>done</>\n", id), Stream::Stdout).unwrap();
+//! }
+//! write!(log, "total: 100").unwrap();
+//! ```
+//! 
+//! You can escape any text to display it as is
+//! 
+//! ```
+//! use colored_str::{colored, coloredln, escape};
+//! 
+//! println!("{}", colored(&format!("<red>{}</>", escape("Vec<u8>"))));
+//! coloredln!("<red>Vec\\<u8></>");
+//! ```
+//! 
+//! You can name styles in a theme, to define them once
+//! 
+//! ```
+//! use colored_str::{colored_with, coloredln, set_theme, Theme};
+//! 
+//! let theme = Theme::new()
+//!     .with("error", "red+bold")
+//!     .with("path", "cyan+underline");
+//! println!("{}", colored_with("<error>cannot open</> <path>/tmp/file</>", &theme));
+//! set_theme(theme);
+//! coloredln!("<error>cannot open</> <path>{}</>", "/tmp/file");
+//! ```
+//! 
+//! A theme can extend another one, and themes can be paired for light and dark backgrounds,
+//! the background being detected from `COLORFGBG` unless set in code
+//! 
+//! ```
+//! use colored_str::{coloredln, set_background, set_theme_pair, Background, Theme, ThemePair};
+//! 
+//! let dark = Theme::new().with("error", "lred+bold").with("path", "lcyan");
+//! let light = Theme::with_parent(dark.clone()).with("path", "blue");
+//! set_theme_pair(ThemePair::new(light, dark));
+//! set_background(Some(Background::Light));
+//! coloredln!("<error>cannot open</> <path>{}</>", "/tmp/file");
+//! ```
+//! 
+//! Styles can be set for the current thread while a scope lasts, for instance so that messages
+//! of a library using this crate pick up your styles. Scopes can be nested.
+//! 
+//! ```
+//! use colored_str::{coloredln, scoped_styles, with_styles, Theme};
+//! 
+//! let styles = Theme::new().with("error", "red+bold");
+//! with_styles(&styles, || coloredln!("<error>cannot open</>"));
+//! let _styles = scoped_styles(&styles);
+//! coloredln!("<error>cannot open</>");
+//! ```
+//! 
+//! Themes can also be loaded from TOML or JSON style sheets, with the `toml` or `json` features,
+//! for instance `Theme::from_toml_str("error = 'red+bold'")`.
+//! 
+//! End users can override any style, built-in ones included, with the `COLORED_STR_THEME`
+//! environment variable, for instance `COLORED_STR_THEME="error=lred+bold:path=cyan+underline"`.
+//! Invalid entries are ignored.
+//! 
+//! You can remove styles to get plain text, for instance to write to a log file
+//! 
+//! ```
+//! use colored_str::{colored, strip, strip_ansi};
+//! 
+//! assert_eq!(strip("<red>this is plain text</>"), "this is plain text");
+//! assert_eq!(strip_ansi(&colored("<red>this is plain text</>").to_string()), "this is plain text");
+//! ```
+//! 
+//! Colors are quantized to the color depth of the terminal, detected from `COLORTERM` and `TERM`,
+//! unless set in code. Without colors, decorations are kept.
+//! 
+//! ```
+//! use colored_str::{coloredln, set_color_depth, ColorDepth};
+//! 
+//! set_color_depth(Some(ColorDepth::Ansi256));
+//! coloredln!("<#FF8000>this is orange, from the 256 colors palette</>");
+//! set_color_depth(Some(ColorDepth::NoColor));
+//! coloredln!("<red+bold>this is bold only</>");
+//! ```
+//! 
+//! Texts are colorized if written to a terminal, unless disabled by `NO_COLOR` or `CLICOLOR=0`,
+//! or forced by `CLICOLOR_FORCE`. Standard output and standard error are checked independently.
+//! 
+//! ```
+//! use colored_str::{coloredln, ecoloredln, set_color_choice, with_color_choice, ColorChoice};
+//! 
+//! coloredln!("<red>this is red if the standard output is a terminal</>");
+//! ecoloredln!("<red>this is red if the standard error is a terminal</>");
+//! with_color_choice(ColorChoice::Never, || coloredln!("<red>this is not red</>"));
+//! set_color_choice(ColorChoice::Always);
+//! coloredln!("<red>this is always red</>");
+//! ```
+//! 
 //! ## List of styles
 //! 
 //! ### Colors
@@ -120,6 +319,13 @@
 //! 
 //! All can be used as backgound using `on_` prefix.
 //! 
+//! ### 256 Colors
+//! 
+//! - `c0` to `c255`, or `ansi(0)` to `ansi(255)`, for the xterm 256 colors palette
+//! - `c0` to `c15` being the colors above, `c16` to `c231` a 6×6×6 color cube and `c232` to `c255` a grayscale ramp
+//! 
+//! All can be used as backgound using `on_` prefix.
+//! 
 //! ### Decorations
 //! 
 //! - `bold`
@@ -134,181 +340,211 @@
 //! 
 //! ### True colors
 //! 
-//! - `#RRGGBB`
-//! - `on_#RRGGBB`
+//! - `#RRGGBB` or `#RGB`
+//! - `on_#RRGGBB` or `on_#RGB`
+//! - `#RRGGBBAA`, `#RGBA`, `on_#RRGGBBAA` and `on_#RGBA` are blended against the background
+//!   set with `set_alpha_background()`, the alpha component being ignored if none is set
+//! 
+//! Hexadecimal digits are case insensitive.
+//! 
+//! Colors can also be written with functional notations, components being separated
+//! by commas or spaces, and clamped if out of range:
+//! 
+//! - `rgb(255, 128, 0)` or `rgb(100% 50% 0%)`
+//! - `hsl(210, 50%, 40%)`, hue in degrees
+//! - `oklch(0.7 0.1 200)` or `oklch(70% 0.1 200)`, hue in degrees
+//! - `on_rgb(...)`, `on_hsl(...)` and `on_oklch(...)` for the background
+//! 
+//! ### Named colors
+//! 
+//! - all [CSS named colors](https://developer.mozilla.org/en-US/docs/Web/CSS/named-color), such as `orange`, `teal` or `rebeccapurple`
+//! - `on_orange`, `on_teal`... for the background
+//! 
+//! Names are case insensitive. Terminal colors listed above, such as `green`, have priority
+//! over CSS colors of the same name.
+//! 
+//! ### Removals
+//! 
+//! - `default` removes the foreground color
+//! - `on_default` removes the background color
+//! - `!bold`, `!underline`... removes a decoration
 //! 
 
-use std::borrow::Cow;
-
-use regex::Regex;
-use regex::Replacer;
-use regex::Captures;
-use regex::CaptureMatches;
-use lazy_static::lazy_static;
+use std::fmt;
+use std::fmt::Write;
 
 use colored::*;
 
-/// Regex to check truecolor foreground format
-fn is_truecolor(text: &str) -> bool
-{
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"^#[ABCDEF\d]{6}$").unwrap();
-    }
-    RE.is_match(text)
-}
+mod color;
+pub use color::{set_alpha_background, alpha_background};
 
-/// Regex to check truecolor background format
-fn is_on_truecolor(text: &str) -> bool
-{
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"^on_#[ABCDEF\d]{6}$").unwrap();
-    }
-    RE.is_match(text)
-}
+mod terminal;
+pub use terminal::{set_color_depth, color_depth, ColorDepth};
+pub use terminal::{set_color_choice, color_choice, with_color_choice, scoped_color_choice, ColorChoice, ScopedColorChoice, Stream};
+pub use terminal::strip_ansi;
+
+mod theme;
+pub use theme::{set_theme, set_theme_pair, theme, Theme, ThemeError, ThemePair};
+pub use theme::{set_background, background, Background};
+pub use theme::{scoped_styles, with_styles, ScopedStyles};
+use terminal::{Output, Sgr, SgrWriter};
+
+mod markup;
+pub use markup::{parse, escape, Markup, Node, Text, Block};
+
+mod error;
+pub use error::{MarkupError, MarkupErrorKind};
 
-/// Regex to retrieve all styled blocks
-fn get_styles<R>(text: &str, rep: R) -> Cow<str>
-    where R: Replacer
+mod diagnostic;
+pub use diagnostic::{check, report, Diagnostic, DiagnosticKind, Severity};
+
+mod template;
+pub use template::{Template, Args, Rendered};
+
+/// If style is truecolor foreground, hexadecimal, functional or named, returns its components
+fn test_truecolor(style: &str) -> Option<(u8, u8, u8)>
 {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"<([\w\d#+]+?)>((.|\n)*?)</>").unwrap();
-        // static ref RE: Regex = Regex::new(r"<(.+?)>((.|\n)*?)</>").unwrap();
+    if let Some(hex) = style.strip_prefix('#') {
+        return color::parse_hex(hex);
     }
-    RE.replace_all(text, rep)
+    color::parse_function(style).map(|(rgb, _)| rgb).or_else(|| color::css_color(style))
 }
 
-/// Regex to retrieve all style modification subblocks
-fn iter_substyles(text: &str) -> CaptureMatches
+/// If style is truecolor background, hexadecimal, functional or named, returns its components
+fn test_on_truecolor(style: &str) -> Option<(u8, u8, u8)>
 {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"<\+([\w\d#+]+?)>((.|\n)*?)<->").unwrap();
-        // static ref RE: Regex = Regex::new(r"<\+(.+?)>((.|\n)*?)<->").unwrap();
+    let name = color::strip_on(style)?;
+    if let Some(hex) = name.strip_prefix('#') {
+        return color::parse_hex(hex);
     }
-    RE.captures_iter(text)
+    color::parse_function(name).map(|(rgb, _)| rgb).or_else(|| color::css_color(name))
 }
 
-/// If style is truecolor foreground, apply style
-fn test_truecolor(style: &'_ str, content: &ColoredString) -> Option<ColoredString>
+/// A function applying a style to a ColoredString
+type StyleFn = Box<dyn Fn(ColoredString) -> ColoredString>;
+
+/// Returns the function to apply in case generic ColoredString function does not apply
+fn test_other(style: &str) -> Option<StyleFn>
 {
-    if !is_truecolor(style) {
-        return None
+    if let Some((r, g, b)) = test_truecolor(style) {
+        Some(Box::new(move |content: ColoredString| content.truecolor(r, g, b)))
+    } else if let Some((r, g, b)) = test_on_truecolor(style) {
+        Some(Box::new(move |content: ColoredString| content.on_truecolor(r, g, b)))
+    } else if let Some(index) = color::parse_ansi(style) {
+        Some(Box::new(move |content: ColoredString| content.ansi_color(index)))
+    } else if let Some(index) = color::strip_on(style).and_then(color::parse_ansi) {
+        Some(Box::new(move |content: ColoredString| content.on_ansi_color(index)))
+    } else {
+        None
     }
-        
-    let red = &style[1..3];
-    let green = &style[3..5];
-    let blue = &style[5..7];
-    let r = u8::from_str_radix(red, 16).expect("invalid color");
-    let g = u8::from_str_radix(green, 16).expect("invalid color");
-    let b = u8::from_str_radix(blue, 16).expect("invalid color");
-    Some(content.clone().truecolor(r, g, b))
 }
 
-/// If style is backcolor foreground, apply style
-fn test_on_truecolor(style: &'_ str, content: &ColoredString) -> Option<ColoredString>
+/// Returns the function to apply in case style is a removal
+fn test_removal(style: &str) -> Option<StyleFn>
 {
-    if !is_on_truecolor(style) {
-        return None
-    }
-     
-    let red = &style[4..6];
-    let green = &style[6..8];
-    let blue = &style[8..10];
-    let r = u8::from_str_radix(red, 16).expect("invalid color");
-    let g = u8::from_str_radix(green, 16).expect("invalid color");
-    let b = u8::from_str_radix(blue, 16).expect("invalid color");
-    Some(content.clone().on_truecolor(r, g, b))
+    let removed = match style.to_lowercase().as_str() {
+        "default" => return Some(Box::new(|mut content: ColoredString| { content.clear_fgcolor(); content })),
+        "on_default" => return Some(Box::new(|mut content: ColoredString| { content.clear_bgcolor(); content })),
+        "!bold" => Styles::Bold,
+        "!underline" => Styles::Underline,
+        "!italic" => Styles::Italic,
+        "!dimmed" => Styles::Dimmed,
+        "!reverse" => Styles::Reversed,
+        "!reversed" => Styles::Reversed,
+        "!blink" => Styles::Blink,
+        "!hidden" => Styles::Hidden,
+        "!strikethrough" => Styles::Strikethrough,
+        _ => return None
+    };
+    Some(Box::new(move |mut content: ColoredString| { content.style.remove(removed); content }))
 }
 
-/// Returns the function to apply in case generic ColoredString function does not apply
-fn test_other(style: &'_ str) -> impl Fn(ColoredString) -> ColoredString + '_
+/// Returns the function to apply for a given style, if it exists, theme styles having priority over built-in ones
+fn test_style(style: &str, theme: &Theme) -> Option<StyleFn>
 {
-    move |content: ColoredString| {
-        if let Some(result) = test_truecolor(style, &content) {
-            result
-        } else if let Some(result) = test_on_truecolor(style, &content) {
-            result
-        } else {
-            let colored = format!("<{}>{}</>", style, content);
-            ColoredString::from(colored.as_ref())    
-        }
-    }
+    let Some(styles) = theme.get(style) else {
+        return test_builtin(style);
+    };
+    let functions: Vec<StyleFn> = styles.split('+').map(|style| test_builtin(style.trim())).collect::<Option<_>>()?;
+    Some(Box::new(move |content: ColoredString| functions.iter().fold(content, |content, function| function(content))))
 }
 
-/// Returns the function to apply in case generic ColoredString function exists
-fn test_style<'a>(style: &'a str) -> Box<dyn Fn(ColoredString) -> ColoredString + 'a>
+/// Returns the function to apply for a given built-in style, if it exists
+fn test_builtin(style: &str) -> Option<StyleFn>
 {
-    match style.to_lowercase().as_str() {
-
-        "black" => Box::new(&ColoredString::black),
-        "red" => Box::new(&ColoredString::red),
-        "green" => Box::new(&ColoredString::green),
-        "yellow" => Box::new(&ColoredString::yellow),
-        "blue" => Box::new(&ColoredString::blue),
-        "magenta" => Box::new(&ColoredString::magenta),
-        "purple" => Box::new(&ColoredString::purple),
-        "cyan" => Box::new(&ColoredString::cyan),
-        "white" => Box::new(&ColoredString::white),
-        "lblack" => Box::new(&ColoredString::bright_black),
-        "lred" => Box::new(&ColoredString::bright_red),
-        "lgreen" => Box::new(&ColoredString::bright_green),
-        "lyellow" => Box::new(&ColoredString::bright_yellow),
-        "lblue" => Box::new(&ColoredString::bright_blue),
-        "lmagenta" => Box::new(&ColoredString::bright_magenta),
-        "lpurple" => Box::new(&ColoredString::bright_purple),
-        "lcyan" => Box::new(&ColoredString::bright_cyan),
-        "lwhite" => Box::new(&ColoredString::bright_white),
-
-        "on_black" => Box::new(&ColoredString::on_black),
-        "on_red" => Box::new(&ColoredString::on_red),
-        "on_green" => Box::new(&ColoredString::on_green),
-        "on_yellow" => Box::new(&ColoredString::on_yellow),
-        "on_blue" => Box::new(&ColoredString::on_blue),
-        "on_magenta" => Box::new(&ColoredString::on_magenta),
-        "on_purple" => Box::new(&ColoredString::on_purple),
-        "on_cyan" => Box::new(&ColoredString::on_cyan),
-        "on_white" => Box::new(&ColoredString::on_white),
-        "on_lblack" => Box::new(&ColoredString::on_bright_black),
-        "on_lred" => Box::new(&ColoredString::on_bright_red),
-        "on_lgreen" => Box::new(&ColoredString::on_bright_green),
-        "on_lyellow" => Box::new(&ColoredString::on_bright_yellow),
-        "on_lblue" => Box::new(&ColoredString::on_bright_blue),
-        "on_lmagenta" => Box::new(&ColoredString::on_bright_magenta),
-        "on_lpurple" => Box::new(&ColoredString::on_bright_purple),
-        "on_lcyan" => Box::new(&ColoredString::on_bright_cyan),
-        "on_lwhite" => Box::new(&ColoredString::on_bright_white),
-
-        "bold" => Box::new(&ColoredString::bold),
-        "underline" => Box::new(&ColoredString::underline),
-        "italic" => Box::new(&ColoredString::italic),
-        "dimmed" => Box::new(&ColoredString::dimmed),
-        "reverse" => Box::new(&ColoredString::reverse),
-        "reversed" => Box::new(&ColoredString::reversed),
-        "blink" => Box::new(&ColoredString::blink),
-        "hidden" => Box::new(&ColoredString::hidden),
-        "strikethrough" => Box::new(&ColoredString::strikethrough),
-
-        _ => Box::new(test_other(style))
-    }
+    let function: StyleFn = match style.to_lowercase().as_str() {
+
+        "black" => Box::new(ColoredString::black),
+        "red" => Box::new(ColoredString::red),
+        "green" => Box::new(ColoredString::green),
+        "yellow" => Box::new(ColoredString::yellow),
+        "blue" => Box::new(ColoredString::blue),
+        "magenta" => Box::new(ColoredString::magenta),
+        "purple" => Box::new(ColoredString::purple),
+        "cyan" => Box::new(ColoredString::cyan),
+        "white" => Box::new(ColoredString::white),
+        "lblack" => Box::new(ColoredString::bright_black),
+        "lred" => Box::new(ColoredString::bright_red),
+        "lgreen" => Box::new(ColoredString::bright_green),
+        "lyellow" => Box::new(ColoredString::bright_yellow),
+        "lblue" => Box::new(ColoredString::bright_blue),
+        "lmagenta" => Box::new(ColoredString::bright_magenta),
+        "lpurple" => Box::new(ColoredString::bright_purple),
+        "lcyan" => Box::new(ColoredString::bright_cyan),
+        "lwhite" => Box::new(ColoredString::bright_white),
+
+        "on_black" => Box::new(ColoredString::on_black),
+        "on_red" => Box::new(ColoredString::on_red),
+        "on_green" => Box::new(ColoredString::on_green),
+        "on_yellow" => Box::new(ColoredString::on_yellow),
+        "on_blue" => Box::new(ColoredString::on_blue),
+        "on_magenta" => Box::new(ColoredString::on_magenta),
+        "on_purple" => Box::new(ColoredString::on_purple),
+        "on_cyan" => Box::new(ColoredString::on_cyan),
+        "on_white" => Box::new(ColoredString::on_white),
+        "on_lblack" => Box::new(ColoredString::on_bright_black),
+        "on_lred" => Box::new(ColoredString::on_bright_red),
+        "on_lgreen" => Box::new(ColoredString::on_bright_green),
+        "on_lyellow" => Box::new(ColoredString::on_bright_yellow),
+        "on_lblue" => Box::new(ColoredString::on_bright_blue),
+        "on_lmagenta" => Box::new(ColoredString::on_bright_magenta),
+        "on_lpurple" => Box::new(ColoredString::on_bright_purple),
+        "on_lcyan" => Box::new(ColoredString::on_bright_cyan),
+        "on_lwhite" => Box::new(ColoredString::on_bright_white),
+
+        "bold" => Box::new(ColoredString::bold),
+        "underline" => Box::new(ColoredString::underline),
+        "italic" => Box::new(ColoredString::italic),
+        "dimmed" => Box::new(ColoredString::dimmed),
+        "reverse" => Box::new(ColoredString::reversed),
+        "reversed" => Box::new(ColoredString::reversed),
+        "blink" => Box::new(ColoredString::blink),
+        "hidden" => Box::new(ColoredString::hidden),
+        "strikethrough" => Box::new(ColoredString::strikethrough),
+
+        _ => return test_removal(style).or_else(|| test_other(style))
+    };
+    Some(function)
 }
 
 /// Overwrite the style of first entry with the style of second entry
 fn update_with_style(text: ColoredString, colored: &ColoredString) -> ColoredString
 {
     let mut result = text;
-    if let Some(fgcolor) = colored.fgcolor() {
+    if let Some(fgcolor) = colored.fgcolor {
         result = result.color(fgcolor);
     }
-    if let Some(bgcolor) = colored.bgcolor() {
+    if let Some(bgcolor) = colored.bgcolor {
         result = result.on_color(bgcolor);
     }
-    if colored.style().contains(Styles::Bold) { result = result.bold(); }
-    if colored.style().contains(Styles::Underline) { result = result.underline(); }
-    if colored.style().contains(Styles::Italic) { result = result.italic(); }
-    if colored.style().contains(Styles::Dimmed) { result = result.dimmed(); }
-    if colored.style().contains(Styles::Reversed) { result = result.reverse(); }
-    if colored.style().contains(Styles::Blink) { result = result.blink(); }
-    if colored.style().contains(Styles::Hidden) { result = result.hidden(); }
-    if colored.style().contains(Styles::Strikethrough) { result = result.strikethrough(); }
+    if colored.style.contains(Styles::Bold) { result = result.bold(); }
+    if colored.style.contains(Styles::Underline) { result = result.underline(); }
+    if colored.style.contains(Styles::Italic) { result = result.italic(); }
+    if colored.style.contains(Styles::Dimmed) { result = result.dimmed(); }
+    if colored.style.contains(Styles::Reversed) { result = result.reversed(); }
+    if colored.style.contains(Styles::Blink) { result = result.blink(); }
+    if colored.style.contains(Styles::Hidden) { result = result.hidden(); }
+    if colored.style.contains(Styles::Strikethrough) { result = result.strikethrough(); }
     result
 }
 
@@ -320,24 +556,83 @@ fn set_style_from(text: &str, colored: &ColoredString) -> ColoredString
     result
 }
 
-/// Add a given ColoredString style to an already styled text (overwrite content)
-fn add_style_from(colored_from: &ColoredString, colored_to: &ColoredString) -> ColoredString
+/// Build the style described by a `+` separated list of styles on top of a given style
+///
+/// Returns `None` if one of the styles is unknown.
+fn build_style(styles: &str, base: &ColoredString, theme: &Theme) -> Option<ColoredString>
 {
-    let mut result = set_style_from(colored_to, colored_from);
-    result = update_with_style(result, colored_to);
-    result
+    let mut result = set_style_from("", base);
+    for style in styles.split('+') {
+        result = test_style(style.trim(), theme)?(result);
+    }
+    Some(result)
 }
 
+/// Give the text gathered so far with given style to `write`, if not empty
+fn flush<F>(text: &mut String, style: &ColoredString, write: &mut F) -> fmt::Result
+where F: FnMut(&ColoredString, &str) -> fmt::Result
+{
+    if !text.is_empty() {
+        write(style, text)?;
+        text.clear();
+    }
+    Ok(())
+}
 
+/// Walk given nodes with given style for given theme, giving each text sharing a style to `write`
+///
+/// Text is gathered in `current` until the style changes, the same buffer being reused for the
+/// whole text. Blocks with unknown styles are kept as text.
+fn style_nodes<F>(nodes: &[Node], style: &ColoredString, current: &mut String, theme: &Theme, write: &mut F) -> fmt::Result
+where F: FnMut(&ColoredString, &str) -> fmt::Result
+{
+    for node in nodes {
+        let (block, base, open, close) = match node {
+            Node::Text(text) => {
+                current.push_str(&text.text);
+                continue;
+            },
+            Node::Block(block) => (block, ColoredString::default(), "<", "</>"),
+            Node::Variation(block) => (block, style.clone(), "<+", "<->")
+        };
+        match build_style(&block.styles, &base, theme) {
+            Some(new_style) => {
+                flush(current, style, write)?;
+                style_nodes(&block.children, &new_style, current, theme, write)?;
+                flush(current, &new_style, write)?;
+            },
+            None => {
+                current.push_str(open);
+                current.push_str(&block.styles);
+                current.push('>');
+                style_nodes(&block.children, style, current, theme, write)?;
+                current.push_str(close);
+            }
+        }
+    }
+    Ok(())
+}
 
+/// Walk a parsed text for given theme, giving each text sharing a style to `write`, in order
+fn walk<F>(markup: &Markup, theme: &Theme, mut write: F) -> fmt::Result
+where F: FnMut(&ColoredString, &str) -> fmt::Result
+{
+    let mut current = String::new();
+    let style = ColoredString::default();
+    style_nodes(&markup.nodes, &style, &mut current, theme, &mut write)?;
+    flush(&mut current, &style, &mut write)
+}
 
-
-
-
-
-
-
-
+/// Split a parsed text into segments of text sharing a style, for given theme
+fn segments(markup: &Markup, theme: &Theme) -> Vec<(ColoredString, String)>
+{
+    let mut segments = vec![];
+    walk(markup, theme, |style, text| {
+        segments.push((style.clone(), text.to_string()));
+        Ok(())
+    }).expect("gathering segments never fails");
+    segments
+}
 
 /// Creates a new [`ColoredString`][1] by parsing given text.
 ///
@@ -345,6 +640,13 @@ fn add_style_from(colored_from: &ColoredString, colored_to: &ColoredString) -> C
 /// subblocks, to add corresponding styles to the text. It then returns a new
 /// instance of [`ColoredString`][1].
 ///
+/// Blocks and subblocks can be nested at any depth: each closing flag restores the
+/// style of the enclosing block. Only the changes of style are written between texts,
+/// such as turning bold off at the end of a `<+bold>` subblock, instead of a reset.
+///
+/// Whether to colorize is checked against the standard output, see [`colored_for()`]
+/// for other streams.
+///
 /// [1]: <https://docs.rs/colored/latest/colored/struct.ColoredString.html>
 /// 
 /// # Examples
@@ -354,65 +656,135 @@ fn add_style_from(colored_from: &ColoredString, colored_to: &ColoredString) -> C
 /// ```
 /// use colored_str::colored;
 /// 
-/// println!("{}", colored("<red>this is red text</red>"));
+/// println!("{}", colored("<red>this is red text</>"));
 /// ```
 /// 
 /// See [crate] for other examples
 pub fn colored(text: &str) -> ColoredString
 {
-    let updated = get_styles(text, |caps: &Captures| {
-
-        // Create main styled item based on capture
-        let combined = caps[1].split('+');
-        let mut item = ColoredString::from(&caps[2]);
-        for style in combined {
-            item = test_style(style.trim())(item);
-        }
-
-        let mut items: Vec<ColoredString> = vec![];
-        let mut id_start = 0;
-        let mut id_end = 0;
-
-        // Add substyles
-        for caps in iter_substyles(&item) {
-
-            let range = caps.get(0).unwrap().range();
-            id_end = range.end;
+    parse(text).colored()
+}
 
-            // Add styled item before the capture if text is not empty
-            if id_start != range.start {
-                let text = &item[id_start..range.start];
-                items.push(set_style_from(text, &item));
-                id_start = id_end;
-            }
+/// Creates a new [`ColoredString`][1] by parsing given text, to be written to given stream.
+///
+/// Same as [`colored()`], which renders for the standard output, but checks whether
+/// to colorize against given stream, as set by [`set_color_choice()`].
+///
+/// [1]: <https://docs.rs/colored/latest/colored/struct.ColoredString.html>
+/// 
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{colored_for, Stream};
+/// 
+/// eprintln!("{}", colored_for("<red>this is red text</>", Stream::Stderr));
+/// ```
+pub fn colored_for(text: &str, stream: Stream) -> ColoredString
+{
+    ColoredString::from(render(&parse(text), Output::new(stream), &theme::current_theme()))
+}
 
-            // Add substyled item if not empty
-            if !&caps[2].is_empty() {
-                let combined = caps[1].split('+');
-                let mut subitem = ColoredString::from(&caps[2]);
-                for style in combined {
-                    subitem = test_style(style.trim())(subitem);
-                }
-                subitem = add_style_from(&item, &subitem);
-                items.push(subitem.clone());
-            }
-        }
+/// Creates a new [`ColoredString`][1] by parsing given text with given theme.
+///
+/// Same as [`colored()`], which uses the theme set by [`set_theme()`], but with given theme.
+/// Names of the theme have priority over built-in styles.
+///
+/// [1]: <https://docs.rs/colored/latest/colored/struct.ColoredString.html>
+/// 
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{colored_with, Theme};
+/// 
+/// let theme = Theme::new().with("warning", "yellow+bold");
+/// println!("{}", colored_with("<warning>this is yellow and bold</>", &theme));
+/// ```
+pub fn colored_with(text: &str, theme: &Theme) -> ColoredString
+{
+    ColoredString::from(render(&parse(text), Output::new(Stream::Stdout), &theme::with_env_overrides(theme)))
+}
 
-        // Add styled item after all captures (end of the string)
-        if id_end != item.len() {
-            let text = &item[id_end..item.len()];
-            items.push(set_style_from(text, &item));
-        }
+/// Writes given text, parsed as per [`colored()`], to given writer, for given stream.
+///
+/// Same as [`colored_for()`], but the rendered text is written in a single pass to any
+/// [`fmt::Write`], such as a [`String`] or a [`fmt::Formatter`], without any intermediate string.
+///
+/// # Errors
+///
+/// Returns an error if writing to the writer fails.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{write_colored, Stream};
+/// 
+/// let mut text = String::new();
+/// write_colored(&mut text, "<red>this is red text</>", Stream::Stdout).unwrap();
+/// println!("{}", text);
+/// ```
+pub fn write_colored<W: fmt::Write + ?Sized>(out: &mut W, text: &str, stream: Stream) -> fmt::Result
+{
+    render_to(out, &parse(text), Output::new(stream), &theme::current_theme())
+}
 
-        // Join all items
-        let mut res = "".to_owned();
-        for i in items {
-            res = format!("{}{}", res, &i);
-        }
-        res
-    });
+/// Creates a new [`String`] by parsing given text, without any style.
+///
+/// Same as [`colored()`] with colors disabled: flags are removed, while unknown styles
+/// and flags that do not match are kept as text, and escaped characters are unescaped.
+/// Use [`strip_ansi()`] to remove styles from an already rendered text.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::strip;
+/// 
+/// assert_eq!(strip("<red>this is <+bold>plain<-></> text"), "this is plain text");
+/// assert_eq!(strip("<rde>this is kept</>"), "<rde>this is kept</>");
+/// ```
+pub fn strip(text: &str) -> String
+{
+    parse(text).stripped()
+}
 
-    ColoredString::from(updated.as_ref())
+/// Creates a new [`ColoredString`][1] by strictly parsing given text.
+///
+/// Same as [`colored()`], but returns the first error found instead of keeping
+/// unknown styles and flags that do not match as text.
+///
+/// [1]: <https://docs.rs/colored/latest/colored/struct.ColoredString.html>
+/// 
+/// # Errors
+///
+/// Returns a [`MarkupError`] locating the offending flag if a style is unknown,
+/// a hexadecimal color is invalid, or if a flag has no matching flag.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{try_colored, MarkupErrorKind};
+/// 
+/// assert!(try_colored("<red>this is red text</>").is_ok());
+///
+/// let error = try_colored("this is\n<rde>red text</>").unwrap_err();
+/// assert_eq!(error.kind, MarkupErrorKind::UnknownStyle("rde".to_string()));
+/// assert_eq!((error.offset, error.line, error.column), (8, 2, 1));
+/// ```
+pub fn try_colored(text: &str) -> Result<ColoredString, MarkupError>
+{
+    match error::markup_errors(text).into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(colored(text))
+    }
 }
 
 /// The trait that enables a string to be colorized
@@ -422,20 +794,201 @@ pub trait Colored
     /// 
     /// [1]: <https://docs.rs/colored/latest/colored/struct.ColoredString.html>
     fn colored(self) -> ColoredString;
+
+    /// Creates a new [`String`] by parsing given text, without any style, as [`strip()`] does.
+    fn stripped(self) -> String;
 }
 
-impl<'a> Colored for &'a str
+impl Colored for &str
 {
     fn colored(self) -> ColoredString
     {
         colored(self)
     }
+
+    fn stripped(self) -> String
+    {
+        strip(self)
+    }
+}
+
+impl Colored for &Markup
+{
+    fn colored(self) -> ColoredString
+    {
+        ColoredString::from(render(self, Output::new(Stream::Stdout), &theme::current_theme()))
+    }
+
+    fn stripped(self) -> String
+    {
+        render(self, Output { colorize: false, depth: ColorDepth::NoColor }, &theme::current_theme())
+    }
+}
+
+/// Render a parsed text to given writer, for given output and theme
+///
+/// Only the changes of style are written between texts sharing a style.
+fn render_to<W: fmt::Write + ?Sized>(out: &mut W, markup: &Markup, output: Output, theme: &Theme) -> fmt::Result
+{
+    let mut writer = SgrWriter::new(out);
+    walk(markup, theme, |style, text| {
+        writer.set_style(Sgr::new(style, output))?;
+        writer.write_str(text)
+    })?;
+    writer.finish()
+}
+
+/// Render a parsed text for given output and theme
+///
+/// The result is written in a single pass, to a buffer sized after the source text.
+fn render(markup: &Markup, output: Output, theme: &Theme) -> String
+{
+    let len = markup.nodes.last().map_or(0, |node| node.span().end);
+    let mut result = String::with_capacity(len + len / 4);
+    render_to(&mut result, markup, output, theme).expect("writing to a string never fails");
+    result
+}
+
+#[doc(hidden)]
+pub mod __private
+{
+    use std::fmt;
+    use std::fmt::Write;
+
+    pub use colored_str_macros::format_colored;
+
+    use crate::terminal::{Output, Sgr, SgrWriter};
+    use crate::{theme, ColorDepth, Stream};
+
+    /// Writer escaping everything written to a markup text
+    struct Escaper<'a>(&'a mut String);
+
+    impl fmt::Write for Escaper<'_>
+    {
+        fn write_str(&mut self, s: &str) -> fmt::Result
+        {
+            self.0.push_str(&crate::escape(s));
+            Ok(())
+        }
+    }
+
+    /// Push formatted arguments to a markup text, escaped
+    pub fn push_escaped(markup: &mut String, args: fmt::Arguments)
+    {
+        Escaper(markup).write_fmt(args).expect("a formatting trait implementation returned an error");
+    }
+
+    /// Push formatted arguments to a markup text, as is
+    pub fn push_markup(markup: &mut String, args: fmt::Arguments)
+    {
+        markup.write_fmt(args).expect("a formatting trait implementation returned an error");
+    }
+
+    /// Check whether a format string baked for given color depth can be written to given stream
+    ///
+    /// Returns whether to colorize, or `None` if the format string must be rendered at runtime,
+    /// as some styles may be defined by a theme, or the color depth is not enough. Only reads
+    /// settings, the environment and the terminal being checked once.
+    pub fn baked(stream: Stream, depth: ColorDepth) -> Option<bool>
+    {
+        if !theme::has_no_styles() {
+            return None;
+        }
+        let output = Output::new(stream);
+        match output.colorize {
+            true => (output.depth >= depth).then_some(true),
+            false => Some(false)
+        }
+    }
+
+    /// Markup text rendered at runtime, written in a single pass when displayed
+    pub struct Runtime<'a>
+    {
+        /// Markup text
+        markup: &'a str,
+        /// Stream the text is written to
+        stream: Stream,
+    }
+
+    impl<'a> Runtime<'a>
+    {
+        /// Creates a markup text rendered for given stream
+        pub fn new(markup: &'a str, stream: Stream) -> Runtime<'a>
+        {
+            Runtime { markup, stream }
+        }
+    }
+
+    impl fmt::Display for Runtime<'_>
+    {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+        {
+            crate::write_colored(f, self.markup, self.stream)
+        }
+    }
+
+    /// Text of a baked format string sharing a style
+    pub struct Segment<'a>
+    {
+        /// Style of the text, with the codes of its colors
+        style: Sgr<&'static str>,
+        /// Formatted arguments
+        args: fmt::Arguments<'a>,
+    }
+
+    impl<'a> Segment<'a>
+    {
+        /// Creates a text with given decorations, as bits in the order written by colored, and
+        /// given codes of colors, empty if none
+        pub fn new(decorations: u8, fgcolor: &'static str, bgcolor: &'static str, args: fmt::Arguments<'a>) -> Segment<'a>
+        {
+            let code = |code: &'static str| Some(code).filter(|code| !code.is_empty());
+            Segment { style: Sgr { decorations, fgcolor: code(fgcolor), bgcolor: code(bgcolor) }, args }
+        }
+    }
+
+    /// Texts of a baked format string, written with only the changes of style between them
+    pub struct Baked<'a>
+    {
+        /// Texts, in order
+        segments: &'a [Segment<'a>],
+    }
+
+    impl<'a> Baked<'a>
+    {
+        /// Creates a baked format string from its texts
+        pub fn new(segments: &'a [Segment<'a>]) -> Baked<'a>
+        {
+            Baked { segments }
+        }
+    }
+
+    impl fmt::Display for Baked<'_>
+    {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+        {
+            let mut writer = SgrWriter::new(f);
+            for segment in self.segments {
+                writer.set_style(segment.style)?;
+                writer.write_fmt(segment.args)?;
+            }
+            writer.finish()
+        }
+    }
 }
 
 /// Creates a new [`String`] by parsing given text.
 ///
 /// With nothing given returns an empty [`String`].  
-/// Otherwise format given parameters using `format!` macro then apply [`colored()`].
+/// Otherwise format given parameters as per `format!` macro then apply [`colored()`].
+/// Arguments are escaped to be displayed as is, unless formatted with `{:markup}`.
+///
+/// The given text is checked at compile time: invalid hexadecimal colors, invalid color functions,
+/// flags that do not match, and unknown styles close to a built-in style, are compile errors.
+/// Other unknown styles are accepted, as they may be defined by a theme.
+///
+/// Texts made of built-in styles, `#RRGGBB`, `#RGB` and `cN` colors are rendered at compile time,
+/// and only at runtime if a theme is set or if the color depth of the terminal is not enough.
 ///
 /// # Examples
 ///
@@ -444,30 +997,52 @@ impl<'a> Colored for &'a str
 /// ```
 /// use colored_str::cformat;
 /// 
-/// println!("{}", cformat!("<red>this is red text</red>"));
-/// println!("{}", cformat!("<red>this is {} text</red>", "red"));
+/// println!("{}", cformat!("<red>this is red text</>"));
+/// println!("{}", cformat!("<red>this is {} text</>", "red"));
 /// ```
 /// 
+/// Typos are compile errors:
+///
+/// ```compile_fail
+/// use colored_str::cformat;
+/// 
+/// println!("{}", cformat!("<rde>this is red text</>"));
+/// ```
+/// 
+/// ```compile_fail
+/// use colored_str::cformat;
+/// 
+/// println!("{}", cformat!("<#FF000>this is red text</>"));
+/// ```
+/// 
+/// ```compile_fail
+/// use colored_str::cformat;
+/// 
+/// println!("{}", cformat!("<red>this is red text"));
+/// ```
+/// 
+/// Flags that do not match are only compile errors if their styles are built-in or likely typos,
+/// others, such as `<div>` or `Vec<{}>`, being displayed as is.
+/// 
 /// See [crate] for other examples
 #[macro_export]
 macro_rules! cformat {
     () => {
         String::from("")
     };
-    ($top:tt) => ({
-        let msg = format!($top);
-        $crate::colored(&msg).to_string()
-    });
-    ($top:tt, $($arg:tt)*) => ({
-        let msg = format!($top, $($arg)*);
-        $crate::colored(&msg).to_string()
-    });
+    ($top:tt) => {
+        $crate::__private::format_colored!(format, Stdout, $crate, $top)
+    };
+    ($top:tt, $($arg:tt)*) => {
+        $crate::__private::format_colored!(format, Stdout, $crate, $top, $($arg)*)
+    };
 }
 
 /// Print colored text to standard output.
 ///
 /// With nothing given does nothing.  
-/// Otherwise format given parameters using `format!` macro, apply [`colored()`], then [`print!`] to standard output.
+/// Otherwise format given parameters as per `format!` macro, apply [`colored()`], then [`print!`] to standard output.
+/// Arguments are escaped to be displayed as is, unless formatted with `{:markup}`.
 ///
 /// # Examples
 ///
@@ -476,8 +1051,8 @@ macro_rules! cformat {
 /// ```
 /// use colored_str::colored;
 /// 
-/// colored!("<red>this is red text</red>");
-/// colored!("<red>this is {} text</red>", "red");
+/// colored!("<red>this is red text</>");
+/// colored!("<red>this is {} text</>", "red");
 /// ```
 /// 
 /// See [crate] for other examples
@@ -487,12 +1062,10 @@ macro_rules! colored {
         print!()
     };
     ($top:tt) => {
-        let msg = format!($top);
-        print!("{}", $crate::colored(&msg));
+        $crate::__private::format_colored!(print, Stdout, $crate, $top)
     };
     ($top:tt, $($arg:tt)*) => {
-        let msg = format!($top, $($arg)*);
-        print!("{}", $crate::colored(&msg));
+        $crate::__private::format_colored!(print, Stdout, $crate, $top, $($arg)*)
     };
 }
 
@@ -500,7 +1073,8 @@ macro_rules! colored {
 /// Print colored text to standard output with newline at the end.
 ///
 /// With nothing given does nothing.  
-/// Otherwise format given parameters using `format!` macro, apply [`colored()`], then [`println!`] to standard output.
+/// Otherwise format given parameters as per `format!` macro, apply [`colored()`], then [`println!`] to standard output.
+/// Arguments are escaped to be displayed as is, unless formatted with `{:markup}`.
 ///
 /// # Examples
 ///
@@ -509,8 +1083,8 @@ macro_rules! colored {
 /// ```
 /// use colored_str::coloredln;
 /// 
-/// coloredln!("<red>this is red text</red>");
-/// coloredln!("<red>this is {} text</red>", "red");
+/// coloredln!("<red>this is red text</>");
+/// coloredln!("<red>this is {} text</>", "red");
 /// ```
 /// 
 /// See [crate] for other examples
@@ -520,11 +1094,71 @@ macro_rules! coloredln {
         println!()
     };
     ($top:tt) => {
-        let msg = format!($top);
-        println!("{}", $crate::colored(&msg));
+        $crate::__private::format_colored!(println, Stdout, $crate, $top)
+    };
+    ($top:tt, $($arg:tt)*) => {
+        $crate::__private::format_colored!(println, Stdout, $crate, $top, $($arg)*)
+    };
+}
+
+/// Print colored text to standard error.
+///
+/// With nothing given does nothing.  
+/// Otherwise format given parameters as per `format!` macro, apply [`colored_for()`], then [`eprint!`] to standard error.
+/// Arguments are escaped to be displayed as is, unless formatted with `{:markup}`.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::ecolored;
+/// 
+/// ecolored!("<red>this is red text</>");
+/// ecolored!("<red>this is {} text</>", "red");
+/// ```
+/// 
+/// See [crate] for other examples
+#[macro_export]
+macro_rules! ecolored {
+    () => {
+        eprint!()
+    };
+    ($top:tt) => {
+        $crate::__private::format_colored!(eprint, Stderr, $crate, $top)
+    };
+    ($top:tt, $($arg:tt)*) => {
+        $crate::__private::format_colored!(eprint, Stderr, $crate, $top, $($arg)*)
+    };
+}
+
+/// Print colored text to standard error with newline at the end.
+///
+/// With nothing given does nothing.  
+/// Otherwise format given parameters as per `format!` macro, apply [`colored_for()`], then [`eprintln!`] to standard error.
+/// Arguments are escaped to be displayed as is, unless formatted with `{:markup}`.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::ecoloredln;
+/// 
+/// ecoloredln!("<red>this is red text</>");
+/// ecoloredln!("<red>this is {} text</>", "red");
+/// ```
+/// 
+/// See [crate] for other examples
+#[macro_export]
+macro_rules! ecoloredln {
+    () => {
+        eprintln!()
+    };
+    ($top:tt) => {
+        $crate::__private::format_colored!(eprintln, Stderr, $crate, $top)
     };
     ($top:tt, $($arg:tt)*) => {
-        let msg = format!($top, $($arg)*);
-        println!("{}", $crate::colored(&msg));
+        $crate::__private::format_colored!(eprintln, Stderr, $crate, $top, $($arg)*)
     };
 }
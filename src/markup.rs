@@ -0,0 +1,355 @@
+// Copyright (C) 2023 Sebastien Guerri
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Markup parsing into a tree of nodes
+
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+
+use colored_str_syntax::{read_flag, unescape, FlagKind};
+
+/// Kind of a token found while scanning a text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenKind
+{
+    /// Raw text
+    Text,
+    /// `<...>` block opening flag
+    Open,
+    /// `</>` block closing flag
+    Close,
+    /// `<+...>` variation opening flag
+    VarOpen,
+    /// `<->` variation closing flag
+    VarClose,
+}
+
+/// A token found while scanning a text
+#[derive(Debug, Clone)]
+pub(crate) struct Token<'a>
+{
+    /// Kind of the token
+    pub kind: TokenKind,
+    /// Source text of the token
+    pub source: &'a str,
+    /// Style list of opening flags, empty otherwise
+    pub styles: &'a str,
+    /// Byte range of the token in the parsed text
+    pub span: Range<usize>,
+}
+
+impl From<FlagKind> for TokenKind
+{
+    fn from(kind: FlagKind) -> TokenKind
+    {
+        match kind {
+            FlagKind::Open => TokenKind::Open,
+            FlagKind::Close => TokenKind::Close,
+            FlagKind::VarOpen => TokenKind::VarOpen,
+            FlagKind::VarClose => TokenKind::VarClose
+        }
+    }
+}
+
+/// Split given text into tokens
+///
+/// Escaped characters are part of text tokens.
+pub(crate) fn tokenize(text: &str) -> Vec<Token<'_>>
+{
+    let mut tokens: Vec<Token> = vec![];
+    let mut id_text = 0;
+    let mut id = 0;
+
+    let text_token = |span: Range<usize>| Token {
+        kind: TokenKind::Text,
+        source: &text[span.clone()],
+        styles: "",
+        span
+    };
+
+    while let Some(pos) = text[id..].find(['<', '\\']) {
+        let start = id + pos;
+        if text[start..].starts_with('\\') {
+            id = match text[start + 1..].starts_with(['<', '\\']) {
+                true => start + 2,
+                false => start + 1
+            };
+            continue;
+        }
+        match read_flag(&text[start..], None) {
+            Some((kind, len)) => {
+                let kind = TokenKind::from(kind);
+                if id_text != start {
+                    tokens.push(text_token(id_text..start));
+                }
+                let source = &text[start..start + len];
+                let styles = match kind {
+                    TokenKind::Open => &source[1..len - 1],
+                    TokenKind::VarOpen => &source[2..len - 1],
+                    _ => ""
+                };
+                tokens.push(Token { kind, source, styles, span: start..start + len });
+                id = start + len;
+                id_text = id;
+            },
+            None => id = start + 1
+        }
+    }
+
+    if id_text != text.len() {
+        tokens.push(text_token(id_text..text.len()));
+    }
+    tokens
+}
+
+/// Find matching opening and closing flags
+///
+/// Returns for each token whether it is part of a matching pair.
+/// Variations are only allowed within a block, unmatched flags are kept as text.
+pub(crate) fn match_flags(tokens: &[Token]) -> Vec<bool>
+{
+    let mut matched = vec![false; tokens.len()];
+    let mut stack: Vec<usize> = vec![];
+
+    for (id, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::Text => {},
+            TokenKind::Open => stack.push(id),
+            TokenKind::VarOpen => {
+                if stack.iter().any(|&i| tokens[i].kind == TokenKind::Open) {
+                    stack.push(id);
+                }
+            },
+            TokenKind::Close => {
+                if stack.iter().any(|&i| tokens[i].kind == TokenKind::Open) {
+                    while let Some(open) = stack.pop() {
+                        if tokens[open].kind == TokenKind::Open {
+                            matched[open] = true;
+                            matched[id] = true;
+                            break;
+                        }
+                    }
+                }
+            },
+            TokenKind::VarClose => {
+                if let Some(&open) = stack.last() {
+                    if tokens[open].kind == TokenKind::VarOpen {
+                        stack.pop();
+                        matched[open] = true;
+                        matched[id] = true;
+                    }
+                }
+            }
+        }
+    }
+    matched
+}
+
+/// A parsed markup text, as returned by [`parse()`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Markup
+{
+    /// Top level nodes
+    pub nodes: Vec<Node>,
+}
+
+/// A node of a [`Markup`] tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node
+{
+    /// Raw text, including flags that do not match
+    Text(Text),
+    /// `<...> * </>` styled block
+    Block(Block),
+    /// `<+...> * <->` style variation block
+    Variation(Block),
+}
+
+/// A raw text node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Text
+{
+    /// Content of the text, escaped characters being unescaped
+    pub text: String,
+    /// Byte range of the text in the parsed source
+    pub span: Range<usize>,
+}
+
+/// A styled block or variation node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block
+{
+    /// `+` separated list of styles, as written in the opening flag
+    pub styles: String,
+    /// Content of the block
+    pub children: Vec<Node>,
+    /// Byte range of the whole block in the parsed source, flags included
+    pub span: Range<usize>,
+    /// Byte range of the opening flag in the parsed source
+    pub open_span: Range<usize>,
+    /// Byte range of the closing flag in the parsed source
+    pub close_span: Range<usize>,
+}
+
+impl Node
+{
+    /// Byte range of the node in the parsed source
+    pub fn span(&self) -> Range<usize>
+    {
+        match self {
+            Node::Text(text) => text.span.clone(),
+            Node::Block(block) | Node::Variation(block) => block.span.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Markup
+{
+    /// Writes the markup back, escaping unmatched flags and backslashes so that it renders as the source
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        self.nodes.iter().try_for_each(|node| node.fmt(f))
+    }
+}
+
+impl fmt::Display for Node
+{
+    /// Writes the markup of the node back, escaping its text
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self {
+            Node::Text(text) => f.write_str(&escape(&text.text)),
+            Node::Block(block) => {
+                write!(f, "<{}>", block.styles)?;
+                block.children.iter().try_for_each(|node| node.fmt(f))?;
+                f.write_str("</>")
+            },
+            Node::Variation(block) => {
+                write!(f, "<+{}>", block.styles)?;
+                block.children.iter().try_for_each(|node| node.fmt(f))?;
+                f.write_str("<->")
+            }
+        }
+    }
+}
+
+/// Escapes given text so that it is displayed as is by [`colored()`](crate::colored()).
+///
+/// Each `<` is written `\<` and each `\` is written `\\`, so that the text never
+/// contains any flag. Returns the given text if there is nothing to escape.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{colored, escape};
+///
+/// let text = "Vec<u8>";
+/// assert_eq!(escape(text), "Vec\\<u8>");
+/// println!("{}", colored(&format!("<red>{}</>", escape(text))));
+/// ```
+pub fn escape(text: &str) -> Cow<'_, str>
+{
+    if !text.contains(['<', '\\']) {
+        return Cow::Borrowed(text);
+    }
+    let mut result = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        if c == '<' || c == '\\' {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    Cow::Owned(result)
+}
+
+/// Push a text to a list of nodes, merging it with the previous text node if any
+fn push_text(nodes: &mut Vec<Node>, source: &str, span: Range<usize>)
+{
+    let text = unescape(source);
+    if let Some(Node::Text(last)) = nodes.last_mut() {
+        if last.span.end == span.start {
+            last.text.push_str(&text);
+            last.span.end = span.end;
+            return;
+        }
+    }
+    nodes.push(Node::Text(Text { text: text.into_owned(), span }));
+}
+
+/// Parses given text into a [`Markup`] tree.
+///
+/// Each `<...> * </>` block and `<+...> * <->` variation becomes a node holding its
+/// styles and its content, with the byte ranges of the block and its flags in the
+/// given text. Flags that do not match are kept in text nodes.
+///
+/// Styles are not checked: a block with an unknown style is still a block node.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{parse, Node};
+///
+/// let markup = parse("this is <red>red</>");
+/// assert_eq!(markup.nodes.len(), 2);
+/// if let Node::Block(block) = &markup.nodes[1] {
+///     assert_eq!(block.styles, "red");
+///     assert_eq!(block.span, 8..19);
+/// }
+/// assert_eq!(markup.to_string(), "this is <red>red</>");
+/// ```
+pub fn parse(text: &str) -> Markup
+{
+    let tokens = tokenize(text);
+    let matched = match_flags(&tokens);
+
+    // Each entry is an opened block, with its opening token and its content so far
+    let mut stack: Vec<(Option<&Token>, Vec<Node>)> = vec![(None, vec![])];
+
+    for (token, matched) in tokens.iter().zip(matched) {
+        match token.kind {
+            TokenKind::Open | TokenKind::VarOpen if matched => {
+                stack.push((Some(token), vec![]));
+            },
+            TokenKind::Close | TokenKind::VarClose if matched => {
+                let (open, children) = stack.pop().expect("closing flag is matched");
+                let open = open.expect("closing flag is matched");
+                let block = Block {
+                    styles: open.styles.to_string(),
+                    children,
+                    span: open.span.start..token.span.end,
+                    open_span: open.span.clone(),
+                    close_span: token.span.clone()
+                };
+                let (_, nodes) = stack.last_mut().expect("root is never closed");
+                nodes.push(match open.kind {
+                    TokenKind::Open => Node::Block(block),
+                    _ => Node::Variation(block)
+                });
+            },
+            _ => {
+                let (_, nodes) = stack.last_mut().expect("root is never closed");
+                push_text(nodes, token.source, token.span.clone());
+            }
+        }
+    }
+
+    let (_, nodes) = stack.pop().expect("root is never closed");
+    Markup { nodes }
+}
@@ -0,0 +1,279 @@
+// Copyright (C) 2023 Sebastien Guerri
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Markup texts parsed once and rendered many times
+
+use std::fmt;
+use std::fmt::Write;
+
+use colored::{Color, ColoredString};
+
+use crate::error::{line_column, MarkupError, MarkupErrorKind};
+use crate::terminal::{Output, Sgr, SgrWriter, Stream};
+use crate::theme::{self, Theme};
+
+/// Reference to an argument within a template
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ArgRef
+{
+    /// Positional argument
+    Index(usize),
+    /// Named argument
+    Name(String),
+}
+
+/// A piece of the text of a segment
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part
+{
+    /// Literal text
+    Text(String),
+    /// Argument to display, with its placeholder as written
+    Arg(ArgRef, String),
+}
+
+/// Text sharing a style
+#[derive(Debug, Clone)]
+struct Segment
+{
+    /// Style of the text
+    style: ColoredString,
+    /// Pieces of the text
+    parts: Vec<Part>,
+}
+
+/// Split given text into literal texts and placeholders, `next` being the next implicit positional argument
+///
+/// Returns the invalid placeholder and its byte offset in the text on error.
+fn split_placeholders(text: &str, next: &mut usize) -> Result<Vec<Part>, (String, usize)>
+{
+    let mut parts = vec![];
+    let mut literal = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find(['{', '}']) {
+        literal.push_str(&rest[..start]);
+        let offset = text.len() - rest.len() + start;
+        rest = &rest[start..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            literal.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        let end = match rest.starts_with('{') {
+            true => rest.find('}').ok_or_else(|| (rest.to_string(), offset))?,
+            false => return Err(("}".to_string(), offset))
+        };
+        let (placeholder, name) = (&rest[..=end], &rest[1..end]);
+        let arg = if name.is_empty() {
+            *next += 1;
+            ArgRef::Index(*next - 1)
+        } else if name.chars().all(|c| c.is_ascii_digit()) {
+            ArgRef::Index(name.parse().map_err(|_| (placeholder.to_string(), offset))?)
+        } else if name.starts_with(|c: char| c.is_alphabetic() || c == '_') && name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            ArgRef::Name(name.to_string())
+        } else {
+            return Err((placeholder.to_string(), offset));
+        };
+        if !literal.is_empty() {
+            parts.push(Part::Text(std::mem::take(&mut literal)));
+        }
+        parts.push(Part::Arg(arg, placeholder.to_string()));
+        rest = &rest[end + 1..];
+    }
+
+    literal.push_str(rest);
+    if !literal.is_empty() {
+        parts.push(Part::Text(literal));
+    }
+    Ok(parts)
+}
+
+/// A markup text parsed once, with its styles resolved, to be rendered many times
+///
+/// Placeholders are written `{}`, `{0}` or `{name}`, and `{{` and `}}` stand for `{` and `}`.
+/// Unlike `format!`, format specs such as `{:>6}` or `{0:?}` are not accepted: arguments are
+/// displayed as is, with the style of their placeholder.
+///
+/// Styles are resolved when compiling, with the theme set at that time, while whether to
+/// colorize and the color depth are checked when rendering.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{Args, Template};
+///
+/// let template = Template::compile("<red>{}</> is <bold>{name}</>").unwrap();
+/// for id in 0..3 {
+///     println!("{}", template.render(Args::new().arg(&id).named("name", &"<bold>")));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Template
+{
+    /// Texts sharing a style, in order
+    segments: Vec<Segment>,
+}
+
+impl Template
+{
+    /// Parses given text once, with the theme set by [`set_theme()`](crate::set_theme()).
+    ///
+    /// Unknown styles and flags that do not match are kept as text, as [`colored()`](crate::colored()) does.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`MarkupError`] of kind [`MarkupErrorKind::BadPlaceholder`] if a placeholder is invalid.
+    pub fn compile(text: &str) -> Result<Template, MarkupError>
+    {
+        Template::compile_resolved(text, &theme::current_theme())
+    }
+
+    /// Parses given text once, with given theme, see [`compile()`](Template::compile()).
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`MarkupError`] of kind [`MarkupErrorKind::BadPlaceholder`] if a placeholder is invalid.
+    pub fn compile_with(text: &str, theme: &Theme) -> Result<Template, MarkupError>
+    {
+        Template::compile_resolved(text, &theme::with_env_overrides(theme))
+    }
+
+    /// Parses given text once, with given theme, already overridden by the environment
+    fn compile_resolved(text: &str, theme: &Theme) -> Result<Template, MarkupError>
+    {
+        if let Err((placeholder, offset)) = split_placeholders(text, &mut 0) {
+            let (line, column) = line_column(text, offset);
+            let kind = MarkupErrorKind::BadPlaceholder(placeholder.clone());
+            return Err(MarkupError { kind, tag: placeholder, offset, line, column });
+        }
+        let mut next = 0;
+        let segments = crate::segments(&crate::parse(text), theme).into_iter()
+            .map(|(style, text)| {
+                let parts = split_placeholders(&text, &mut next).unwrap_or_else(|_| vec![Part::Text(text)]);
+                Segment { style, parts }
+            })
+            .collect();
+        Ok(Template { segments })
+    }
+
+    /// Renders the template with given arguments, to be written to the standard output
+    ///
+    /// Placeholders without argument are kept as written.
+    pub fn render<'a>(&'a self, args: Args<'a>) -> Rendered<'a>
+    {
+        self.render_for(args, Stream::Stdout)
+    }
+
+    /// Renders the template with given arguments, to be written to given stream
+    ///
+    /// Same as [`render()`](Template::render()), but checks whether to colorize against given stream.
+    pub fn render_for<'a>(&'a self, args: Args<'a>, stream: Stream) -> Rendered<'a>
+    {
+        let output = Output::new(stream);
+        let styles = self.segments.iter().map(|segment| Sgr::new(&segment.style, output)).collect();
+        Rendered { template: self, args, styles }
+    }
+}
+
+/// Arguments of a [`Template`], positional and named
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{Args, Template};
+///
+/// let template = Template::compile("{0} {name} {1}").unwrap();
+/// let text = template.render(Args::new().arg(&1).arg(&"two").named("name", &3.5)).to_string();
+/// assert_eq!(text, "1 3.5 two");
+/// ```
+#[derive(Clone, Default)]
+pub struct Args<'a>
+{
+    /// Positional arguments, in order
+    positional: Vec<&'a dyn fmt::Display>,
+    /// Named arguments
+    named: Vec<(&'a str, &'a dyn fmt::Display)>,
+}
+
+impl<'a> Args<'a>
+{
+    /// Creates an empty list of arguments
+    pub fn new() -> Args<'a>
+    {
+        Args::default()
+    }
+
+    /// Adds a positional argument
+    pub fn arg(mut self, value: &'a dyn fmt::Display) -> Args<'a>
+    {
+        self.positional.push(value);
+        self
+    }
+
+    /// Adds a named argument
+    pub fn named(mut self, name: &'a str, value: &'a dyn fmt::Display) -> Args<'a>
+    {
+        self.named.push((name, value));
+        self
+    }
+
+    /// Returns the argument given reference stands for, if any
+    fn get(&self, arg: &ArgRef) -> Option<&'a dyn fmt::Display>
+    {
+        match arg {
+            ArgRef::Index(index) => self.positional.get(*index).copied(),
+            ArgRef::Name(name) => self.named.iter().find(|(n, _)| n == name).map(|(_, value)| *value)
+        }
+    }
+}
+
+/// A [`Template`] rendered with its arguments, as returned by [`Template::render()`]
+///
+/// Escape codes are computed when rendering, arguments being displayed when the rendered template is displayed.
+pub struct Rendered<'a>
+{
+    /// Rendered template
+    template: &'a Template,
+    /// Arguments of the template
+    args: Args<'a>,
+    /// Escape codes of the segments, for the stream the template is written to
+    styles: Vec<Sgr<Color>>,
+}
+
+impl fmt::Display for Rendered<'_>
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let mut writer = SgrWriter::new(f);
+        for (segment, style) in self.template.segments.iter().zip(&self.styles) {
+            writer.set_style(*style)?;
+            for part in &segment.parts {
+                match part {
+                    Part::Text(literal) => writer.write_str(literal)?,
+                    Part::Arg(arg, placeholder) => match self.args.get(arg) {
+                        Some(value) => write!(writer, "{}", value)?,
+                        None => writer.write_str(placeholder)?
+                    }
+                }
+            }
+        }
+        writer.finish()
+    }
+}
@@ -0,0 +1,776 @@
+// Copyright (C) 2023 Sebastien Guerri
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Terminal capabilities and escape sequences
+
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::env;
+use std::fmt;
+use std::io::IsTerminal;
+use std::marker::PhantomData;
+use std::sync::RwLock;
+
+use colored::{Color, ColoredString, Styles};
+use colored_str_syntax::DECORATIONS;
+
+use crate::color;
+
+/// Number of colors a terminal can display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ColorDepth
+{
+    /// No color, decorations such as bold or underline being kept
+    NoColor,
+    /// The 16 terminal colors
+    Ansi16,
+    /// The xterm 256 colors palette
+    Ansi256,
+    /// 24-bit colors
+    TrueColor,
+}
+
+/// A value detected from the environment once, until reset
+pub(crate) struct Detected<T>(RwLock<Option<T>>);
+
+impl<T: Clone> Detected<T>
+{
+    /// Creates a value not detected yet
+    pub const fn new() -> Detected<T>
+    {
+        Detected(RwLock::new(None))
+    }
+
+    /// Returns the value, detected by given function if not detected yet
+    pub fn get(&self, detect: impl FnOnce() -> T) -> T
+    {
+        if let Some(value) = &*self.0.read().unwrap_or_else(|e| e.into_inner()) {
+            return value.clone();
+        }
+        let value = detect();
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = Some(value.clone());
+        value
+    }
+
+    /// Forgets the value, so that it is detected again
+    pub fn reset(&self)
+    {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+/// Color depth set in code, overriding the detected one
+static COLOR_DEPTH: RwLock<Option<ColorDepth>> = RwLock::new(None);
+
+/// Color depth detected from the environment
+static DETECTED_DEPTH: Detected<ColorDepth> = Detected::new();
+
+impl ColorDepth
+{
+    /// Detects the color depth of the terminal from the `COLORTERM` and `TERM` environment variables.
+    ///
+    /// `COLORTERM` set to `truecolor` or `24bit`, or a `TERM` ending with `direct`, means
+    /// [`ColorDepth::TrueColor`], a `TERM` ending with `256color` means [`ColorDepth::Ansi256`],
+    /// and `TERM` set to `dumb` means [`ColorDepth::NoColor`]. Defaults to [`ColorDepth::Ansi16`].
+    pub fn detect() -> ColorDepth
+    {
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        let term = env::var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" || term.ends_with("direct") {
+            ColorDepth::TrueColor
+        } else if term.ends_with("256color") {
+            ColorDepth::Ansi256
+        } else if term == "dumb" {
+            ColorDepth::NoColor
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+}
+
+/// Sets the color depth colors are quantized to, overriding the detected one.
+///
+/// With `None`, which is the default, the color depth is detected by [`ColorDepth::detect()`],
+/// once, then again after each call.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{colored, set_color_depth, ColorDepth};
+///
+/// set_color_depth(Some(ColorDepth::Ansi256));
+/// println!("{}", colored("<#FF8000>this is orange, from the 256 colors palette</>"));
+/// # set_color_depth(None);
+/// ```
+pub fn set_color_depth(depth: Option<ColorDepth>)
+{
+    *COLOR_DEPTH.write().unwrap_or_else(|e| e.into_inner()) = depth;
+    DETECTED_DEPTH.reset();
+}
+
+/// Returns the color depth colors are quantized to, either set by [`set_color_depth()`] or detected
+pub fn color_depth() -> ColorDepth
+{
+    let depth = *COLOR_DEPTH.read().unwrap_or_else(|e| e.into_inner());
+    depth.unwrap_or_else(|| DETECTED_DEPTH.get(ColorDepth::detect))
+}
+
+/// Whether to colorize texts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ColorChoice
+{
+    /// Colorize if the stream is a terminal, unless disabled by the environment
+    #[default]
+    Auto,
+    /// Always colorize
+    Always,
+    /// Never colorize
+    Never,
+}
+
+/// A standard stream texts are written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stream
+{
+    /// Standard output
+    Stdout,
+    /// Standard error
+    Stderr,
+}
+
+/// Color choice set in code
+static COLOR_CHOICE: RwLock<ColorChoice> = RwLock::new(ColorChoice::Auto);
+
+/// Whether to colorize texts written to each stream with [`ColorChoice::Auto`], detected from the environment
+static AUTO_COLORIZE: [Detected<bool>; 2] = [Detected::new(), Detected::new()];
+
+thread_local! {
+    /// Color choice set for the current scope of the current thread, overriding the global one
+    static SCOPED_COLOR_CHOICE: Cell<Option<ColorChoice>> = const { Cell::new(None) };
+}
+
+/// Check if an environment variable is set and not empty
+fn is_set(name: &str) -> bool
+{
+    env::var_os(name).is_some_and(|value| !value.is_empty())
+}
+
+impl ColorChoice
+{
+    /// Check if texts written to given stream should be colorized.
+    ///
+    /// With [`ColorChoice::Auto`], a non-empty `NO_COLOR` disables colors, then a `CLICOLOR_FORCE`
+    /// other than `0` enables them, then `CLICOLOR` set to `0` disables them. Otherwise texts are
+    /// colorized if the stream is a terminal.
+    pub fn should_colorize(self, stream: Stream) -> bool
+    {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                if is_set("NO_COLOR") {
+                    false
+                } else if env::var_os("CLICOLOR_FORCE").is_some_and(|value| !value.is_empty() && value != "0") {
+                    true
+                } else if env::var_os("CLICOLOR").is_some_and(|value| value == "0") {
+                    false
+                } else {
+                    match stream {
+                        Stream::Stdout => std::io::stdout().is_terminal(),
+                        Stream::Stderr => std::io::stderr().is_terminal()
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Sets whether to colorize texts, for all threads.
+///
+/// Defaults to [`ColorChoice::Auto`]. Use [`with_color_choice()`] to set it for a scope only.
+///
+/// With [`ColorChoice::Auto`], the environment and the streams are checked once, then again after each call.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{colored, set_color_choice, ColorChoice};
+///
+/// set_color_choice(ColorChoice::Never);
+/// assert_eq!(colored("<red>this is not red</>").to_string(), "this is not red");
+/// # set_color_choice(ColorChoice::Auto);
+/// ```
+pub fn set_color_choice(choice: ColorChoice)
+{
+    *COLOR_CHOICE.write().unwrap_or_else(|e| e.into_inner()) = choice;
+    AUTO_COLORIZE.iter().for_each(Detected::reset);
+}
+
+/// Returns whether to colorize texts, either set for the current scope or for all threads
+pub fn color_choice() -> ColorChoice
+{
+    SCOPED_COLOR_CHOICE.with(Cell::get).unwrap_or_else(|| *COLOR_CHOICE.read().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Restores the previous scoped color choice when dropped, see [`scoped_color_choice()`]
+#[must_use = "the color choice is only set until the guard is dropped"]
+pub struct ScopedColorChoice
+{
+    /// Color choice to restore
+    previous: Option<ColorChoice>,
+    /// Scoped color choices are thread local, so is the guard
+    thread: PhantomData<*const ()>,
+}
+
+impl Drop for ScopedColorChoice
+{
+    fn drop(&mut self)
+    {
+        SCOPED_COLOR_CHOICE.with(|choice| choice.set(self.previous));
+    }
+}
+
+/// Sets given color choice for the current thread, until the returned guard is dropped.
+///
+/// Guards can be nested, and must be dropped in reverse order.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{colored, scoped_color_choice, ColorChoice};
+///
+/// let _choice = scoped_color_choice(ColorChoice::Never);
+/// assert_eq!(colored("<red>this is not red</>").to_string(), "this is not red");
+/// ```
+pub fn scoped_color_choice(choice: ColorChoice) -> ScopedColorChoice
+{
+    let previous = SCOPED_COLOR_CHOICE.with(|scoped| scoped.replace(Some(choice)));
+    ScopedColorChoice { previous, thread: PhantomData }
+}
+
+/// Runs given function with given color choice, for the current thread only.
+///
+/// The previous color choice is restored afterwards, even if the function panics,
+/// so that calls can be nested. Useful in tests, which run in parallel threads.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{colored, with_color_choice, ColorChoice};
+///
+/// let text = with_color_choice(ColorChoice::Always, || colored("<red>this is red</>").to_string());
+/// assert_eq!(text, "\x1B[31mthis is red\x1B[0m");
+/// ```
+pub fn with_color_choice<R>(choice: ColorChoice, f: impl FnOnce() -> R) -> R
+{
+    let _scope = scoped_color_choice(choice);
+    f()
+}
+
+/// How texts are rendered for a stream
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Output
+{
+    /// Whether to colorize texts
+    pub colorize: bool,
+    /// Color depth colors are quantized to
+    pub depth: ColorDepth,
+}
+
+impl Output
+{
+    /// Returns how texts are rendered for given stream
+    pub fn new(stream: Stream) -> Output
+    {
+        let colorize = match color_choice() {
+            ColorChoice::Auto => AUTO_COLORIZE[stream as usize].get(|| ColorChoice::Auto.should_colorize(stream)),
+            choice => choice.should_colorize(stream)
+        };
+        Output { colorize, depth: color_depth() }
+    }
+}
+
+/// The 16 terminal colors, in palette order
+const ANSI_16: [Color; 16] = [
+    Color::Black, Color::Red, Color::Green, Color::Yellow, Color::Blue, Color::Magenta, Color::Cyan, Color::White,
+    Color::BrightBlack, Color::BrightRed, Color::BrightGreen, Color::BrightYellow,
+    Color::BrightBlue, Color::BrightMagenta, Color::BrightCyan, Color::BrightWhite,
+];
+
+/// Returns the color closest to given color that fits given color depth, if any
+fn quantize(color: Color, depth: ColorDepth) -> Option<Color>
+{
+    let rgb = match (depth, color) {
+        (ColorDepth::NoColor, _) => return None,
+        (ColorDepth::TrueColor, _) => return Some(color),
+        (_, Color::TrueColor { r, g, b }) => (r, g, b),
+        (ColorDepth::Ansi16, Color::AnsiColor(index)) if index < 16 => return Some(ANSI_16[index as usize]),
+        (ColorDepth::Ansi16, Color::AnsiColor(index)) => color::ansi_components(index),
+        _ => return Some(color)
+    };
+    match depth {
+        ColorDepth::Ansi256 => Some(Color::AnsiColor(color::closest_ansi(rgb, 16..=255))),
+        _ => Some(ANSI_16[color::closest_ansi(rgb, 0..=15) as usize])
+    }
+}
+
+/// Decorations of colored, in the order of [`DECORATIONS`]
+const DECORATION_STYLES: [Styles; 8] = [
+    Styles::Bold,
+    Styles::Dimmed,
+    Styles::Underline,
+    Styles::Reversed,
+    Styles::Italic,
+    Styles::Blink,
+    Styles::Hidden,
+    Styles::Strikethrough,
+];
+
+/// Codes turning off each decoration of [`DECORATIONS`], bold and dimmed sharing theirs
+const DECORATIONS_OFF: [&str; 8] = ["22", "22", "24", "27", "23", "25", "28", "29"];
+
+/// Sequence resetting every style
+const RESET: &str = "\x1B[0m";
+
+/// Color written as a code of an escape sequence
+pub(crate) trait ColorCode: Copy + PartialEq
+{
+    /// Write the code of the color, as a background color or not
+    fn write_code<W: fmt::Write + ?Sized>(self, out: &mut W, background: bool) -> fmt::Result;
+}
+
+impl ColorCode for Color
+{
+    fn write_code<W: fmt::Write + ?Sized>(self, out: &mut W, background: bool) -> fmt::Result
+    {
+        match (self, background) {
+            (Color::TrueColor { r, g, b }, false) => write!(out, "38;2;{};{};{}", r, g, b),
+            (Color::TrueColor { r, g, b }, true) => write!(out, "48;2;{};{};{}", r, g, b),
+            (_, false) => out.write_str(&self.to_fg_str()),
+            (_, true) => out.write_str(&self.to_bg_str())
+        }
+    }
+}
+
+/// Code baked at compile time, either for the foreground or the background
+impl ColorCode for &'static str
+{
+    fn write_code<W: fmt::Write + ?Sized>(self, out: &mut W, _background: bool) -> fmt::Result
+    {
+        out.write_str(self)
+    }
+}
+
+/// State of the terminal as set by escape sequences
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Sgr<C>
+{
+    /// Decorations, as bits in the order of [`DECORATIONS`]
+    pub decorations: u8,
+    /// Foreground color, if any
+    pub fgcolor: Option<C>,
+    /// Background color, if any
+    pub bgcolor: Option<C>,
+}
+
+impl<C> Default for Sgr<C>
+{
+    fn default() -> Sgr<C>
+    {
+        Sgr { decorations: 0, fgcolor: None, bgcolor: None }
+    }
+}
+
+impl Sgr<Color>
+{
+    /// Returns the state set by given style, as rendered for given output
+    pub fn new(style: &ColoredString, output: Output) -> Sgr<Color>
+    {
+        if !output.colorize {
+            return Sgr::default();
+        }
+        let decorations = DECORATION_STYLES.iter().enumerate()
+            .filter(|(_, decoration)| style.style.contains(**decoration))
+            .fold(0, |bits, (id, _)| bits | 1 << id);
+        Sgr {
+            decorations,
+            fgcolor: style.fgcolor.and_then(|color| quantize(color, output.depth)),
+            bgcolor: style.bgcolor.and_then(|color| quantize(color, output.depth))
+        }
+    }
+}
+
+impl<C: ColorCode> Sgr<C>
+{
+    /// Check if this is the default state
+    fn is_plain(&self) -> bool
+    {
+        *self == Sgr::default()
+    }
+}
+
+/// Codes of a single escape sequence, written as they come
+struct Codes<'a, W: ?Sized>
+{
+    /// Writer the sequence is written to
+    out: &'a mut W,
+    /// Whether some code was written
+    started: bool,
+}
+
+impl<W: fmt::Write + ?Sized> Codes<'_, W>
+{
+    /// Write the start of the sequence or a separator
+    fn separate(&mut self) -> fmt::Result
+    {
+        let separator = if self.started { ";" } else { "\x1B[" };
+        self.started = true;
+        self.out.write_str(separator)
+    }
+
+    /// Write a code
+    fn push(&mut self, code: &str) -> fmt::Result
+    {
+        self.separate()?;
+        self.out.write_str(code)
+    }
+
+    /// Write the code of a color, or the code of the default color if none
+    fn push_color<C: ColorCode>(&mut self, color: Option<C>, background: bool) -> fmt::Result
+    {
+        match color {
+            Some(color) => {
+                self.separate()?;
+                color.write_code(self.out, background)
+            },
+            None => self.push(if background { "49" } else { "39" })
+        }
+    }
+
+    /// Write the end of the sequence, if started
+    fn end(self) -> fmt::Result
+    {
+        match self.started {
+            true => self.out.write_str("m"),
+            false => Ok(())
+        }
+    }
+}
+
+/// Write the escape sequence changing the terminal from given state, `None` if unknown, to the other
+///
+/// Only the decorations and colors that change are written, while going back to the default
+/// state is a reset.
+fn write_transition<W: fmt::Write + ?Sized, C: ColorCode>(out: &mut W, from: Option<Sgr<C>>, to: Sgr<C>) -> fmt::Result
+{
+    if from == Some(to) {
+        return Ok(());
+    }
+    if to.is_plain() {
+        return out.write_str(RESET);
+    }
+
+    let mut codes = Codes { out, started: false };
+    let mut from = match from {
+        Some(from) => from,
+        None => {
+            codes.push("0")?;
+            Sgr::default()
+        }
+    };
+    let off = from.decorations & !to.decorations;
+    for (id, code) in DECORATIONS_OFF.iter().enumerate() {
+        // Bold and dimmed are turned off by the same code, written once
+        if off & 1 << id != 0 && !(id == 1 && off & 1 != 0) {
+            codes.push(code)?;
+        }
+    }
+    if off & 0b11 != 0 {
+        from.decorations &= !0b11;
+    }
+    let on = to.decorations & !from.decorations;
+    for (id, (_, code)) in DECORATIONS.iter().enumerate() {
+        if on & 1 << id != 0 {
+            codes.push(code)?;
+        }
+    }
+    if from.bgcolor != to.bgcolor {
+        codes.push_color(to.bgcolor, true)?;
+    }
+    if from.fgcolor != to.fgcolor {
+        codes.push_color(to.fgcolor, false)?;
+    }
+    codes.end()
+}
+
+/// Effect of an escape sequence embedded within a styled text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Embedded
+{
+    /// Resets every style
+    Reset,
+    /// Turns off some decorations or colors, possibly setting others
+    Clear,
+    /// Only sets decorations or colors
+    Set,
+    /// Does not change the style, such as a cursor move
+    Other,
+}
+
+/// Read the escape sequence at the start of given text, returning its effect and its length
+///
+/// Escape sequences other than control sequences are only read up to the escape character.
+/// Returns `None` if the sequence is not complete.
+fn read_embedded(text: &str) -> Option<(Embedded, usize)>
+{
+    let body = match text.strip_prefix('\x1B')? {
+        "" => return None,
+        rest => match rest.strip_prefix('[') {
+            Some(body) => body,
+            None => return Some((Embedded::Other, 1))
+        }
+    };
+    let end = body.find(|c: char| ('@'..='~').contains(&c))?;
+    let len = end + 3;
+    if !body[end..].starts_with('m') {
+        return Some((Embedded::Other, len));
+    }
+
+    let (mut reset, mut clear) = (true, false);
+    let mut codes = body[..end].split(';');
+    while let Some(code) = codes.next() {
+        // Codes such as `38:2::255:0:0` hold their arguments
+        let number = code.split(':').next().unwrap_or_default();
+        let value = match number.is_empty() {
+            true => Some(0),
+            false => number.parse::<u16>().ok()
+        };
+        match value {
+            Some(0) => clear = true,
+            Some(38 | 48 | 58) if !code.contains(':') => {
+                reset = false;
+                // Components of the color are not codes
+                match codes.next() {
+                    Some("5") => { codes.next(); },
+                    Some("2") => { codes.nth(2); },
+                    _ => {}
+                }
+            },
+            Some(22..=29 | 39 | 49 | 59) => {
+                reset = false;
+                clear = true;
+            },
+            _ => reset = false
+        }
+    }
+    let embedded = match (reset, clear) {
+        (true, _) => Embedded::Reset,
+        (false, true) => Embedded::Clear,
+        (false, false) => Embedded::Set
+    };
+    Some((embedded, len))
+}
+
+/// Writer of texts with a style, only writing the changes of style between texts
+///
+/// The style of a text is written before its first character, so that empty texts write nothing.
+/// Escape sequences within a styled text that turn off some of its style, such as the reset ending
+/// an interpolated [`ColoredString`], are followed by its style again before the next character,
+/// while other escape sequences setting a style leave the terminal state unknown. An escape
+/// sequence split between writes is read once complete. Unstyled texts are written as is.
+pub(crate) struct SgrWriter<'a, W: ?Sized, C>
+{
+    /// Writer the texts are written to
+    out: &'a mut W,
+    /// Style of the text being written
+    style: Sgr<C>,
+    /// State of the terminal, `None` if unknown
+    state: Option<Sgr<C>>,
+    /// Whether the style must be written before the next character
+    pending: bool,
+    /// Start of an escape sequence not complete yet
+    partial: String,
+}
+
+impl<'a, W: fmt::Write + ?Sized, C: ColorCode> SgrWriter<'a, W, C>
+{
+    /// Creates a writer of styled texts to given writer
+    pub fn new(out: &'a mut W) -> SgrWriter<'a, W, C>
+    {
+        SgrWriter { out, style: Sgr::default(), state: Some(Sgr::default()), pending: false, partial: String::new() }
+    }
+
+    /// Sets the style of the next text
+    pub fn set_style(&mut self, style: Sgr<C>) -> fmt::Result
+    {
+        self.flush_partial()?;
+        self.style = style;
+        self.pending = true;
+        Ok(())
+    }
+
+    /// Write a reset if the terminal is not in its default state
+    pub fn finish(mut self) -> fmt::Result
+    {
+        self.flush_partial()?;
+        match self.state == Some(Sgr::default()) {
+            true => Ok(()),
+            false => self.out.write_str(RESET)
+        }
+    }
+
+    /// Write the style of the text if needed
+    fn restore(&mut self) -> fmt::Result
+    {
+        if self.pending {
+            write_transition(self.out, self.state, self.style)?;
+            self.state = Some(self.style);
+            self.pending = false;
+        }
+        Ok(())
+    }
+
+    /// Write characters of the text, after its style
+    fn write_text(&mut self, text: &str) -> fmt::Result
+    {
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.restore()?;
+        self.out.write_str(text)
+    }
+
+    /// Write an escape sequence not complete by the end of the text, as is
+    fn flush_partial(&mut self) -> fmt::Result
+    {
+        if self.partial.is_empty() {
+            return Ok(());
+        }
+        let partial = std::mem::take(&mut self.partial);
+        self.write_text(&partial)?;
+        self.state = None;
+        Ok(())
+    }
+
+    /// Write a styled text, keeping the start of an escape sequence not complete yet
+    fn write_styled(&mut self, text: &str) -> fmt::Result
+    {
+        let mut rest = text;
+        while let Some(start) = rest.find('\x1B') {
+            self.write_text(&rest[..start])?;
+            rest = &rest[start..];
+            let Some((embedded, len)) = read_embedded(rest) else {
+                self.partial.push_str(rest);
+                return Ok(());
+            };
+            match embedded {
+                Embedded::Reset => {
+                    self.state = Some(Sgr::default());
+                    self.pending = true;
+                },
+                Embedded::Clear => {
+                    self.state = None;
+                    self.pending = true;
+                },
+                // The sequence adds to the style of the text
+                Embedded::Set => {
+                    self.restore()?;
+                    self.state = None;
+                },
+                Embedded::Other => {}
+            }
+            self.out.write_str(&rest[..len])?;
+            rest = &rest[len..];
+        }
+        self.write_text(rest)
+    }
+}
+
+impl<W: fmt::Write + ?Sized, C: ColorCode> fmt::Write for SgrWriter<'_, W, C>
+{
+    fn write_str(&mut self, text: &str) -> fmt::Result
+    {
+        if text.is_empty() {
+            return Ok(());
+        }
+        if self.style.is_plain() {
+            return self.write_text(text);
+        }
+        if self.partial.is_empty() {
+            return self.write_styled(text);
+        }
+        let mut text = std::mem::take(&mut self.partial) + text;
+        let result = self.write_styled(&text);
+        text.clear();
+        result
+    }
+}
+
+/// Removes escape sequences from given text, such as the styles of a rendered text.
+///
+/// Returns the given text if there is nothing to remove.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{colored, strip_ansi, with_color_choice, ColorChoice};
+///
+/// let text = with_color_choice(ColorChoice::Always, || colored("<red>this is plain text</>").to_string());
+/// assert_eq!(strip_ansi(&text), "this is plain text");
+/// ```
+pub fn strip_ansi(text: &str) -> Cow<'_, str>
+{
+    if !text.contains('\x1B') {
+        return Cow::Borrowed(text);
+    }
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1B' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            // Control sequence, ended by a character within `@` and `~`
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            },
+            // Operating system command, ended by a bell or a string terminator
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1B' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+    Cow::Owned(result)
+}
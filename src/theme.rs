@@ -0,0 +1,623 @@
+// Copyright (C) 2023 Sebastien Guerri
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Named styles defined at runtime
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{Arc, RwLock};
+
+use lazy_static::lazy_static;
+
+use crate::error::style_error;
+use crate::terminal::Detected;
+#[cfg(any(feature = "toml", feature = "json"))]
+use crate::error::line_column;
+
+/// Environment variable overriding styles, as `name=styles:name=styles`
+const THEME_VARIABLE: &str = "COLORED_STR_THEME";
+
+/// A set of named styles, each name standing for a `+` separated list of built-in styles.
+///
+/// Names are case insensitive, and have priority over built-in styles of the same name.
+/// A theme can extend a parent theme, its own names having priority over the parent ones.
+/// Styles set by the `COLORED_STR_THEME` environment variable have priority over the theme. The
+/// variable is read once, then again each time a theme is set by [`set_theme()`] or [`set_theme_pair()`].
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{colored_with, Theme};
+///
+/// let theme = Theme::new()
+///     .with("error", "red+bold")
+///     .with("path", "cyan+underline");
+/// println!("{}", colored_with("<error>cannot open</> <path>/tmp/file</>", &theme));
+///
+/// let light = Theme::with_parent(theme).with("path", "blue+underline");
+/// assert_eq!(light.get("error"), Some("red+bold"));
+/// assert_eq!(light.get("path"), Some("blue+underline"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Theme
+{
+    /// Styles by lowercase name, shared by the copies of the theme until modified
+    styles: Arc<HashMap<String, String>>,
+    /// Theme whose names are used when not found in this one
+    parent: Option<Arc<Theme>>,
+}
+
+impl Theme
+{
+    /// Creates an empty theme
+    pub fn new() -> Theme
+    {
+        Theme::default()
+    }
+
+    /// Creates an empty theme extending given parent theme
+    pub fn with_parent(parent: Theme) -> Theme
+    {
+        Theme { styles: Arc::default(), parent: Some(Arc::new(parent)) }
+    }
+
+    /// Returns the parent theme, if any
+    pub fn parent(&self) -> Option<&Theme>
+    {
+        self.parent.as_deref()
+    }
+
+    /// Returns the theme with given name standing for given styles
+    pub fn with(mut self, name: &str, styles: &str) -> Theme
+    {
+        self.insert(name, styles);
+        self
+    }
+
+    /// Sets given name to stand for given styles, returning the styles it stood for, if any
+    pub fn insert(&mut self, name: &str, styles: &str) -> Option<String>
+    {
+        Arc::make_mut(&mut self.styles).insert(name.to_lowercase(), styles.to_string())
+    }
+
+    /// Removes given name from this theme, not from its parent, returning the styles it stood for, if any
+    pub fn remove(&mut self, name: &str) -> Option<String>
+    {
+        Arc::make_mut(&mut self.styles).remove(&name.to_lowercase())
+    }
+
+    /// Returns the styles given name stands for, if any, looking into the parent theme if needed
+    pub fn get(&self, name: &str) -> Option<&str>
+    {
+        let name = name.to_lowercase();
+        let mut theme = Some(self);
+        while let Some(current) = theme {
+            if let Some(styles) = current.styles.get(&name) {
+                return Some(styles);
+            }
+            theme = current.parent();
+        }
+        None
+    }
+
+    /// Check if neither the theme nor its parent define any name
+    pub fn is_empty(&self) -> bool
+    {
+        self.styles.is_empty() && self.parent().is_none_or(Theme::is_empty)
+    }
+
+    /// Returns the names of the theme and its parent, lowercase, in arbitrary order
+    pub fn names(&self) -> impl Iterator<Item = &str>
+    {
+        let mut names: Vec<&str> = vec![];
+        let mut theme = Some(self);
+        while let Some(current) = theme {
+            names.extend(current.styles.keys().map(String::as_str));
+            theme = current.parent();
+        }
+        names.sort_unstable();
+        names.dedup();
+        names.into_iter()
+    }
+}
+
+/// An error found while loading a theme from a style sheet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThemeError
+{
+    /// Offending key, if any
+    pub key: Option<String>,
+    /// Description of the error
+    pub message: String,
+    /// Line of the error in the style sheet, starting at 1, if known
+    pub line: Option<usize>,
+}
+
+impl fmt::Display for ThemeError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        f.write_str(&self.message)?;
+        if let Some(key) = &self.key {
+            write!(f, " for key `{}`", key)?;
+        }
+        if let Some(line) = self.line {
+            write!(f, " at line {}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ThemeError {}
+
+impl Theme
+{
+    /// Add an entry loaded from a style sheet, checking its styles
+    fn insert_checked(&mut self, key: &str, value: Option<&str>, line: Option<usize>) -> Result<(), ThemeError>
+    {
+        let error = |message: String| ThemeError { key: Some(key.to_string()), message, line };
+        let styles = value.ok_or_else(|| error("expected a string".to_string()))?;
+        if let Some(kind) = styles.split('+').find_map(|style| style_error(style.trim(), &Theme::default())) {
+            return Err(error(kind.to_string()));
+        }
+        self.insert(key, styles);
+        Ok(())
+    }
+
+    /// Loads a theme from a `name=styles:name=styles` list, as read from `COLORED_STR_THEME`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ThemeError`] naming the first key without `=` or with an invalid style.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use colored_str::Theme;
+    ///
+    /// let theme = Theme::from_env_str("error=lred+bold:path=cyan+underline").unwrap();
+    /// assert_eq!(theme.get("error"), Some("lred+bold"));
+    ///
+    /// let error = Theme::from_env_str("error=lred:path=cyen").unwrap_err();
+    /// assert_eq!(error.to_string(), "unknown style `cyen` for key `path`");
+    /// ```
+    pub fn from_env_str(text: &str) -> Result<Theme, ThemeError>
+    {
+        let mut theme = Theme::new();
+        for entry in text.split(':').filter(|entry| !entry.trim().is_empty()) {
+            let Some((key, styles)) = entry.split_once('=') else {
+                return Err(ThemeError { key: Some(entry.trim().to_string()), message: "expected `=`".to_string(), line: None });
+            };
+            theme.insert_checked(key.trim(), Some(styles.trim()), None)?;
+        }
+        Ok(theme)
+    }
+}
+
+#[cfg(feature = "toml")]
+impl Theme
+{
+    /// Loads a theme from a TOML style sheet, each key standing for a `+` separated list of built-in styles.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ThemeError`] if the style sheet is not valid TOML, or naming the first key,
+    /// in order of appearance, whose value is not a string or has an invalid style.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use colored_str::{colored_with, Theme};
+    ///
+    /// let theme = Theme::from_toml_str("
+    /// error = 'red+bold'
+    /// path = 'cyan+underline'
+    /// ").unwrap();
+    /// println!("{}", colored_with("<error>cannot open</> <path>/tmp/file</>", &theme));
+    ///
+    /// let error = Theme::from_toml_str("error = 'red'\nwarning = 'yelow'").unwrap_err();
+    /// assert_eq!(error.to_string(), "unknown style `yelow` for key `warning` at line 2");
+    /// ```
+    pub fn from_toml_str(text: &str) -> Result<Theme, ThemeError>
+    {
+        use toml::Spanned;
+
+        let table: HashMap<Spanned<String>, Spanned<toml::Value>> = toml::from_str(text).map_err(|error| ThemeError {
+            key: None,
+            message: error.message().to_string(),
+            line: error.span().map(|span| line_column(text, span.start).0)
+        })?;
+
+        let mut entries: Vec<_> = table.into_iter().collect();
+        entries.sort_by_key(|(key, _)| key.span().start);
+
+        let mut theme = Theme::new();
+        for (key, value) in entries {
+            let line = line_column(text, key.span().start).0;
+            theme.insert_checked(key.get_ref(), value.get_ref().as_str(), Some(line))?;
+        }
+        Ok(theme)
+    }
+}
+
+#[cfg(feature = "json")]
+impl Theme
+{
+    /// Loads a theme from a JSON style sheet, each key standing for a `+` separated list of built-in styles.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ThemeError`] if the style sheet is not a valid JSON object, or naming the
+    /// first key, in order of appearance, whose value is not a string or has an invalid style.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use colored_str::{colored_with, Theme};
+    ///
+    /// let theme = Theme::from_json_str(r#"{
+    ///     "error": "red+bold",
+    ///     "path": "cyan+underline"
+    /// }"#).unwrap();
+    /// println!("{}", colored_with("<error>cannot open</> <path>/tmp/file</>", &theme));
+    ///
+    /// let error = Theme::from_json_str("{\n\"error\": \"red\",\n\"warning\": \"yelow\"\n}").unwrap_err();
+    /// assert_eq!(error.to_string(), "unknown style `yelow` for key `warning` at line 3");
+    /// ```
+    pub fn from_json_str(text: &str) -> Result<Theme, ThemeError>
+    {
+        let object: serde_json::Map<String, serde_json::Value> = serde_json::from_str(text).map_err(|error| {
+            let message = error.to_string();
+            let suffix = format!(" at line {} column {}", error.line(), error.column());
+            ThemeError {
+                key: None,
+                message: message.strip_suffix(&suffix).unwrap_or(&message).to_string(),
+                line: Some(error.line())
+            }
+        })?;
+
+        // JSON parsing does not keep positions, so keys are searched in the source
+        let position = |key: &str| -> Option<usize> {
+            let quoted = serde_json::to_string(key).ok()?;
+            text.match_indices(&quoted)
+                .map(|(offset, _)| offset)
+                .find(|offset| text[offset + quoted.len()..].trim_start().starts_with(':'))
+        };
+        let mut entries: Vec<_> = object.iter().map(|(key, value)| (position(key), key, value)).collect();
+        entries.sort_by_key(|(offset, _, _)| offset.unwrap_or(usize::MAX));
+
+        let mut theme = Theme::new();
+        for (offset, key, value) in entries {
+            let line = offset.map(|offset| line_column(text, offset).0);
+            theme.insert_checked(key, value.as_str(), line)?;
+        }
+        Ok(theme)
+    }
+}
+
+/// Background of a terminal, to select a theme of a [`ThemePair`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Background
+{
+    /// Light background, with dark text
+    Light,
+    /// Dark background, with light text
+    Dark,
+}
+
+/// Background set in code, overriding the detected one
+static BACKGROUND: RwLock<Option<Background>> = RwLock::new(None);
+
+/// Background detected from the environment
+static DETECTED_BACKGROUND: Detected<Option<Background>> = Detected::new();
+
+impl Background
+{
+    /// Detects the background of the terminal from the `COLORFGBG` environment variable, if set.
+    ///
+    /// The last field of the variable is the background color, from the 16 terminal colors:
+    /// `7` (white) and `9` to `15` (bright colors except gray) are light, others are dark.
+    pub fn detect() -> Option<Background>
+    {
+        let variable = env::var("COLORFGBG").ok()?;
+        let color: u8 = variable.rsplit(';').next()?.trim().parse().ok()?;
+        match color {
+            7 | 9..=15 => Some(Background::Light),
+            0..=6 | 8 => Some(Background::Dark),
+            _ => None
+        }
+    }
+}
+
+/// Sets the background selecting the theme of a [`ThemePair`], overriding the detected one.
+///
+/// With `None`, which is the default, the background is detected by [`Background::detect()`],
+/// once, then again after each call.
+pub fn set_background(background: Option<Background>)
+{
+    *BACKGROUND.write().unwrap_or_else(|e| e.into_inner()) = background;
+    DETECTED_BACKGROUND.reset();
+}
+
+/// Returns the background selecting the theme of a [`ThemePair`], either set, detected, or dark by default
+pub fn background() -> Background
+{
+    let background = *BACKGROUND.read().unwrap_or_else(|e| e.into_inner());
+    background.or_else(|| DETECTED_BACKGROUND.get(Background::detect)).unwrap_or(Background::Dark)
+}
+
+/// A pair of themes for light and dark backgrounds, selected by [`background()`]
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{Background, Theme, ThemePair};
+///
+/// let dark = Theme::new().with("error", "lred+bold").with("path", "lcyan");
+/// let light = Theme::with_parent(dark.clone()).with("path", "blue");
+/// let themes = ThemePair::new(light, dark);
+/// assert_eq!(themes.select(Background::Light).get("path"), Some("blue"));
+/// assert_eq!(themes.select(Background::Light).get("error"), Some("lred+bold"));
+/// assert_eq!(themes.select(Background::Dark).get("path"), Some("lcyan"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThemePair
+{
+    /// Theme for light backgrounds
+    pub light: Theme,
+    /// Theme for dark backgrounds
+    pub dark: Theme,
+}
+
+impl ThemePair
+{
+    /// Creates a pair of themes for light and dark backgrounds
+    pub fn new(light: Theme, dark: Theme) -> ThemePair
+    {
+        ThemePair { light, dark }
+    }
+
+    /// Returns the theme for given background
+    pub fn select(&self, background: Background) -> &Theme
+    {
+        match background {
+            Background::Light => &self.light,
+            Background::Dark => &self.dark
+        }
+    }
+
+    /// Returns the theme for the current background, as returned by [`background()`]
+    pub fn current(&self) -> &Theme
+    {
+        self.select(background())
+    }
+}
+
+lazy_static! {
+    /// Themes used by default, for light and dark backgrounds
+    static ref THEMES: RwLock<(Arc<Theme>, Arc<Theme>)> = RwLock::new(Default::default());
+}
+
+/// Styles set by the `COLORED_STR_THEME` environment variable, if any
+static ENV_STYLES: Detected<Option<Arc<HashMap<String, String>>>> = Detected::new();
+
+thread_local! {
+    /// Styles set for the current scope of the current thread, overriding the themes
+    static SCOPED_STYLES: RefCell<Option<Arc<HashMap<String, String>>>> = const { RefCell::new(None) };
+}
+
+/// Sets the theme used by [`colored()`](crate::colored()) and the macros, whatever the background.
+///
+/// Defaults to an empty theme.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{coloredln, set_theme, Theme};
+///
+/// set_theme(Theme::new().with("error", "red+bold"));
+/// coloredln!("<error>this is red and bold</>");
+/// ```
+pub fn set_theme(theme: Theme)
+{
+    let theme = Arc::new(theme);
+    *THEMES.write().unwrap_or_else(|e| e.into_inner()) = (theme.clone(), theme);
+    ENV_STYLES.reset();
+}
+
+/// Sets the themes used by [`colored()`](crate::colored()) and the macros, selected when rendering by [`background()`].
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{coloredln, set_theme_pair, Theme, ThemePair};
+///
+/// let dark = Theme::new().with("path", "lcyan+underline");
+/// let light = Theme::with_parent(dark.clone()).with("path", "blue+underline");
+/// set_theme_pair(ThemePair::new(light, dark));
+/// coloredln!("<path>/tmp/file</>");
+/// ```
+pub fn set_theme_pair(themes: ThemePair)
+{
+    *THEMES.write().unwrap_or_else(|e| e.into_inner()) = (Arc::new(themes.light), Arc::new(themes.dark));
+    ENV_STYLES.reset();
+}
+
+/// Returns a copy of the theme used by [`colored()`](crate::colored()) on the current thread,
+/// for the current background and with the scoped styles
+pub fn theme() -> Theme
+{
+    (*default_theme()).clone()
+}
+
+/// Returns the theme used by default for the current background, extended by the scoped styles
+/// and overridden by the `COLORED_STR_THEME` environment variable, as layers sharing their styles
+pub(crate) fn current_theme() -> Arc<Theme>
+{
+    let theme = default_theme();
+    match env_styles() {
+        Some(styles) => Arc::new(Theme { styles, parent: Some(theme) }),
+        None => theme
+    }
+}
+
+/// Returns the theme used by default for the current background, extended by the scoped styles
+fn default_theme() -> Arc<Theme>
+{
+    let theme = {
+        let themes = THEMES.read().unwrap_or_else(|e| e.into_inner());
+        match background() {
+            Background::Light => themes.0.clone(),
+            Background::Dark => themes.1.clone()
+        }
+    };
+    match SCOPED_STYLES.with(|scoped| scoped.borrow().clone()) {
+        Some(styles) => Arc::new(Theme { styles, parent: Some(theme) }),
+        None => theme
+    }
+}
+
+/// Check if no style is defined, by the themes, the scoped styles, or the `COLORED_STR_THEME` environment variable
+pub(crate) fn has_no_styles() -> bool
+{
+    if SCOPED_STYLES.with(|scoped| scoped.borrow().is_some()) {
+        return false;
+    }
+    let themes = THEMES.read().unwrap_or_else(|e| e.into_inner());
+    themes.0.is_empty() && themes.1.is_empty() && env_styles().is_none()
+}
+
+/// Restores the previous scoped styles when dropped, see [`scoped_styles()`]
+#[must_use = "the styles are only set until the guard is dropped"]
+pub struct ScopedStyles
+{
+    /// Styles to restore
+    previous: Option<Arc<HashMap<String, String>>>,
+    /// Scoped styles are thread local, so is the guard
+    thread: PhantomData<*const ()>,
+}
+
+impl Drop for ScopedStyles
+{
+    fn drop(&mut self)
+    {
+        let previous = self.previous.take();
+        SCOPED_STYLES.with(|scoped| *scoped.borrow_mut() = previous);
+    }
+}
+
+/// Sets given styles for the current thread, until the returned guard is dropped.
+///
+/// The styles, including those of the parent of given theme, have priority over the themes set by
+/// [`set_theme()`] or [`set_theme_pair()`], and over the styles of enclosing scopes, so that scopes
+/// can be nested. Guards must be dropped in reverse order.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{colored, scoped_styles, with_color_choice, ColorChoice, Theme};
+///
+/// with_color_choice(ColorChoice::Always, || {
+///     let _styles = scoped_styles(&Theme::new().with("error", "red+bold"));
+///     assert_eq!(colored("<error>failed</>").to_string(), "\x1B[1;31mfailed\x1B[0m");
+/// });
+/// assert_eq!(colored("<error>failed</>").to_string(), "<error>failed</>");
+/// ```
+pub fn scoped_styles(styles: &Theme) -> ScopedStyles
+{
+    SCOPED_STYLES.with(|scoped| {
+        let previous = scoped.borrow().clone();
+        let mut merged = previous.as_deref().cloned().unwrap_or_default();
+        let mut themes = vec![];
+        let mut theme = Some(styles);
+        while let Some(current) = theme {
+            themes.push(current);
+            theme = current.parent();
+        }
+        for theme in themes.into_iter().rev() {
+            merged.extend(theme.styles.iter().map(|(name, styles)| (name.clone(), styles.clone())));
+        }
+        *scoped.borrow_mut() = Some(Arc::new(merged));
+        ScopedStyles { previous, thread: PhantomData }
+    })
+}
+
+/// Runs given function with given styles set for the current thread, see [`scoped_styles()`].
+///
+/// The previous styles are restored afterwards, even if the function panics.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use colored_str::{coloredln, with_styles, Theme};
+///
+/// let styles = Theme::new().with("error", "red+bold");
+/// with_styles(&styles, || coloredln!("<error>this is red and bold</>"));
+/// ```
+pub fn with_styles<R>(styles: &Theme, f: impl FnOnce() -> R) -> R
+{
+    let _scope = scoped_styles(styles);
+    f()
+}
+
+/// Returns the styles set by the `COLORED_STR_THEME` environment variable, parsed once, if any
+///
+/// Invalid entries of the variable are ignored.
+fn env_styles() -> Option<Arc<HashMap<String, String>>>
+{
+    ENV_STYLES.get(|| {
+        let variable = env::var(THEME_VARIABLE).ok()?;
+        let mut styles = HashMap::new();
+        for entry in variable.split(':') {
+            if let Ok(overrides) = Theme::from_env_str(entry) {
+                styles.extend(Arc::unwrap_or_clone(overrides.styles));
+            }
+        }
+        Some(Arc::new(styles)).filter(|styles| !styles.is_empty())
+    })
+}
+
+/// Returns given theme with the styles overridden by the `COLORED_STR_THEME` environment variable,
+/// as a layer sharing the styles of given theme
+pub(crate) fn with_env_overrides(theme: &Theme) -> Cow<'_, Theme>
+{
+    match env_styles() {
+        Some(styles) => Cow::Owned(Theme { styles, parent: Some(Arc::new(theme.clone())) }),
+        None => Cow::Borrowed(theme)
+    }
+}
@@ -0,0 +1,60 @@
+// Copyright (C) 2023 Sebastien Guerri
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use colored_str::*;
+
+/// Background and themes are global, so their assertions are kept in their own test binary
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn theme_pair_assertions()
+    {
+        set_color_choice(ColorChoice::Always);
+
+        std::env::set_var("COLORFGBG", "15;0");
+        assert_eq!(Background::detect(), Some(Background::Dark));
+        std::env::set_var("COLORFGBG", "0;default;15");
+        assert_eq!(Background::detect(), Some(Background::Light));
+        std::env::set_var("COLORFGBG", "0;default");
+        assert_eq!(Background::detect(), None);
+        std::env::remove_var("COLORFGBG");
+        assert_eq!(Background::detect(), None);
+
+        let dark = Theme::new().with("banner", "lyellow").with("title", "bold");
+        let light = Theme::with_parent(dark.clone()).with("banner", "yellow");
+        let themes = ThemePair::new(light, dark);
+        assert_eq!(themes.select(Background::Light).get("banner"), Some("yellow"));
+        assert_eq!(themes.select(Background::Dark).get("banner"), Some("lyellow"));
+
+        set_theme_pair(themes);
+        assert_eq!(background(), Background::Dark);
+        assert_eq!(colored("<banner+title>toto</>").to_string(), "\x1B[1;93mtoto\x1B[0m");
+        set_background(Some(Background::Light));
+        assert_eq!(background(), Background::Light);
+        assert_eq!(colored("<banner+title>toto</>").to_string(), "\x1B[1;33mtoto\x1B[0m");
+        assert_eq!(theme().get("banner"), Some("yellow"));
+        set_background(None);
+        assert_eq!(background(), Background::Dark);
+        std::env::set_var("COLORFGBG", "0;15");
+        assert_eq!(background(), Background::Dark);
+        set_background(None);
+        assert_eq!(background(), Background::Light);
+        std::env::remove_var("COLORFGBG");
+        set_background(None);
+        set_theme(Theme::new());
+    }
+}
@@ -0,0 +1,78 @@
+// Copyright (C) 2023 Sebastien Guerri
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use colored_str::*;
+
+/// Color choice is global, so its assertions are kept in their own test binary
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn choice_assertions()
+    {
+        set_color_choice(ColorChoice::Always);
+
+        assert_eq!(color_choice(), ColorChoice::Always);
+        with_color_choice(ColorChoice::Never, || {
+            assert_eq!(color_choice(), ColorChoice::Never);
+            assert_eq!(colored("<red>toto</>").to_string(), "toto");
+            assert_eq!(colored_for("<red>toto</>", Stream::Stderr).to_string(), "toto");
+            assert_eq!(cformat!("<red>{}</>", "toto"), "toto");
+            with_color_choice(ColorChoice::Always, || {
+                assert_eq!(colored("<red>toto</>").to_string(), "\x1B[31mtoto\x1B[0m");
+            });
+            assert_eq!(colored("<red>toto</>").to_string(), "toto");
+        });
+        assert_eq!(colored_for("<red>toto</>", Stream::Stderr).to_string(), "\x1B[31mtoto\x1B[0m");
+
+        assert!(ColorChoice::Always.should_colorize(Stream::Stdout));
+        assert!(!ColorChoice::Never.should_colorize(Stream::Stderr));
+
+        std::env::set_var("NO_COLOR", "1");
+        std::env::set_var("CLICOLOR_FORCE", "1");
+        assert!(!ColorChoice::Auto.should_colorize(Stream::Stdout));
+        assert!(ColorChoice::Always.should_colorize(Stream::Stdout));
+        std::env::remove_var("NO_COLOR");
+        assert!(ColorChoice::Auto.should_colorize(Stream::Stdout));
+        assert!(ColorChoice::Auto.should_colorize(Stream::Stderr));
+        std::env::set_var("CLICOLOR_FORCE", "0");
+        std::env::set_var("CLICOLOR", "0");
+        assert!(!ColorChoice::Auto.should_colorize(Stream::Stderr));
+        std::env::remove_var("CLICOLOR_FORCE");
+        std::env::remove_var("CLICOLOR");
+
+        std::env::set_var("NO_COLOR", "1");
+        set_color_choice(ColorChoice::Auto);
+        assert_eq!(colored_for("<red>toto</>", Stream::Stderr).to_string(), "toto");
+        std::env::remove_var("NO_COLOR");
+        std::env::set_var("CLICOLOR_FORCE", "1");
+        assert_eq!(colored_for("<red>toto</>", Stream::Stderr).to_string(), "toto");
+        set_color_choice(ColorChoice::Auto);
+        assert_eq!(colored_for("<red>toto</>", Stream::Stderr).to_string(), "\x1B[31mtoto\x1B[0m");
+        std::env::remove_var("CLICOLOR_FORCE");
+
+        // The override of colored only applies to its own strings
+        set_color_choice(ColorChoice::Always);
+        colored::control::set_override(false);
+        assert_eq!(colored("<red>toto</>").to_string(), "\x1B[31mtoto\x1B[0m");
+        assert_eq!(cformat!("<red>{}</>", "toto"), "\x1B[31mtoto\x1B[0m");
+        set_color_choice(ColorChoice::Never);
+        colored::control::set_override(true);
+        assert_eq!(colored("<red>toto</>").to_string(), "toto");
+        assert_eq!(cformat!("<red>{}</>", "toto"), "toto");
+        colored::control::unset_override();
+    }
+}
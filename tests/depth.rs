@@ -0,0 +1,75 @@
+// Copyright (C) 2023 Sebastien Guerri
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use colored_str::*;
+
+/// Color depth is global, so its assertions are kept in their own test binary
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depth_assertions()
+    {
+        set_color_choice(ColorChoice::Always);
+
+        std::env::set_var("COLORTERM", "truecolor");
+        assert_eq!(ColorDepth::detect(), ColorDepth::TrueColor);
+        std::env::remove_var("COLORTERM");
+        std::env::set_var("TERM", "xterm-256color");
+        assert_eq!(ColorDepth::detect(), ColorDepth::Ansi256);
+        std::env::set_var("TERM", "xterm");
+        assert_eq!(ColorDepth::detect(), ColorDepth::Ansi16);
+        std::env::set_var("TERM", "dumb");
+        assert_eq!(ColorDepth::detect(), ColorDepth::NoColor);
+        assert_eq!(color_depth(), ColorDepth::NoColor);
+        std::env::set_var("TERM", "xterm-256color");
+        assert_eq!(color_depth(), ColorDepth::NoColor);
+        set_color_depth(None);
+        assert_eq!(color_depth(), ColorDepth::Ansi256);
+
+        set_color_depth(Some(ColorDepth::TrueColor));
+        assert_eq!(color_depth(), ColorDepth::TrueColor);
+        assert_eq!(colored("<#FF8000+bold>toto</>").to_string(), "\x1B[1;38;2;255;128;0mtoto\x1B[0m");
+        assert_eq!(colored("<c208>toto</>").to_string(), "\x1B[38;5;208mtoto\x1B[0m");
+
+        set_color_depth(Some(ColorDepth::Ansi256));
+        assert_eq!(colored("<#FF8000+bold>toto</>").to_string(), "\x1B[1;38;5;208mtoto\x1B[0m");
+        assert_eq!(colored("<on_#102030>toto</>").to_string(), "\x1B[48;5;234mtoto\x1B[0m");
+        assert_eq!(colored("<#808080>toto</>").to_string(), "\x1B[38;5;244mtoto\x1B[0m");
+        assert_eq!(colored("<red+on_c232>toto</>").to_string(), "\x1B[48;5;232;31mtoto\x1B[0m");
+        assert_eq!(cformat!("<#FF8000+bold>{}</>", "toto"), "\x1B[1;38;5;208mtoto\x1B[0m");
+        assert_eq!(cformat!("<red+on_c232>{}</>", "toto"), "\x1B[48;5;232;31mtoto\x1B[0m");
+
+        set_color_depth(Some(ColorDepth::Ansi16));
+        assert_eq!(colored("<#FF8000+bold>toto</>").to_string(), "\x1B[1;91mtoto\x1B[0m");
+        assert_eq!(colored("<orange+underline>toto</>").to_string(), "\x1B[4;33mtoto\x1B[0m");
+        assert_eq!(colored("<#808080>toto</>").to_string(), "\x1B[90mtoto\x1B[0m");
+        assert_eq!(colored("<c208>toto</>").to_string(), "\x1B[91mtoto\x1B[0m");
+        assert_eq!(colored("<c9>toto</>").to_string(), "\x1B[91mtoto\x1B[0m");
+        assert_eq!(colored("<red+on_c232>toto</>").to_string(), "\x1B[40;31mtoto\x1B[0m");
+        assert_eq!(cformat!("<c9>{}</>", "toto"), "\x1B[91mtoto\x1B[0m");
+        assert_eq!(cformat!("<red+bold>{}</>", "toto"), "\x1B[1;31mtoto\x1B[0m");
+
+        set_color_depth(Some(ColorDepth::NoColor));
+        assert_eq!(colored("<#FF8000+bold>toto</>").to_string(), "\x1B[1mtoto\x1B[0m");
+        assert_eq!(colored("<red+on_blue>toto<+underline>toto<-></>").to_string(), "toto\x1B[4mtoto\x1B[0m");
+        assert_eq!(cformat!("<red+on_blue>toto<+underline>{}<-></>", "toto"), "toto\x1B[4mtoto\x1B[0m");
+        assert_eq!(cformat!("<bold>{}</>", "toto"), "\x1B[1mtoto\x1B[0m");
+
+        set_color_depth(None);
+        assert_eq!(color_depth(), ColorDepth::Ansi256);
+    }
+}
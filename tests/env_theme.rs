@@ -0,0 +1,50 @@
+// Copyright (C) 2023 Sebastien Guerri
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use colored_str::*;
+
+/// The environment is global, so the assertions on `COLORED_STR_THEME` are kept in their own test binary
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_theme_assertions()
+    {
+        set_color_choice(ColorChoice::Always);
+
+        let theme = Theme::from_env_str("error=lred+bold:path = cyan+underline:").unwrap();
+        assert_eq!(theme, Theme::new().with("error", "lred+bold").with("path", "cyan+underline"));
+        let error = Theme::from_env_str("error=lred:path").unwrap_err();
+        assert_eq!((error.key, error.message, error.line), (Some("path".to_string()), "expected `=`".to_string(), None));
+        let error = Theme::from_env_str("error=lred+bodl").unwrap_err();
+        assert_eq!((error.key, error.message), (Some("error".to_string()), "unknown style `bodl`".to_string()));
+
+        std::env::set_var("COLORED_STR_THEME", "notice=lred+bold:lime=blue:invalid=rde:broken");
+        let theme = Theme::new().with("notice", "green").with("hint", "cyan");
+        assert_eq!(colored("<notice>toto</>").to_string(), "\x1B[1;91mtoto\x1B[0m");
+        assert_eq!(colored_with("<notice>toto</><hint>toto</>", &theme).to_string(), "\x1B[1;91mtoto\x1B[22;36mtoto\x1B[0m");
+        assert_eq!(colored("<lime>toto</>").to_string(), "\x1B[34mtoto\x1B[0m");
+        assert_eq!(colored("<invalid>toto</>").to_string(), "<invalid>toto</>");
+        assert_eq!(strip("<notice>toto</>"), "toto");
+        assert!(try_colored("<notice>toto</>").is_ok());
+        assert!(check("<notice>toto</>").is_empty());
+        std::env::remove_var("COLORED_STR_THEME");
+        assert_eq!(colored("<notice>toto</>").to_string(), "\x1B[1;91mtoto\x1B[0m");
+        set_theme(Theme::new());
+        assert_eq!(colored("<notice>toto</>").to_string(), "<notice>toto</>");
+        assert_eq!(colored_with("<notice>toto</>", &theme).to_string(), "\x1B[32mtoto\x1B[0m");
+    }
+}
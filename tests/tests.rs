@@ -19,9 +19,18 @@ use colored_str::*;
 mod tests {
     use super::*;
 
+    /// Force colorization and 24-bit colors whatever the terminal running the tests
+    fn setup()
+    {
+        set_color_choice(ColorChoice::Always);
+        set_color_depth(Some(ColorDepth::TrueColor));
+    }
+
     #[test]
     fn incorrect_assertions()
     {
+        setup();
+
         assert_eq!(colored("<>").to_string(), "<>");
         assert_eq!(colored("<+>").to_string(), "<+>");
         assert_eq!(colored("<->").to_string(), "<->");
@@ -50,6 +59,8 @@ mod tests {
     #[test]
     fn correct_assertions()
     {
+        setup();
+
         assert_eq!(colored("<red></>").to_string(), "");
 
         assert_eq!(colored("<red>toto</>").to_string(), "\x1B[31mtoto\x1B[0m");
@@ -61,10 +72,10 @@ mod tests {
         assert_eq!(colored("<#FF0000>toto</>").to_string(), "\x1B[38;2;255;0;0mtoto\x1B[0m");
         assert_eq!(colored("<on_#FF0000>toto</>").to_string(), "\x1B[48;2;255;0;0mtoto\x1B[0m");
 
-        assert_eq!(colored("<red>toto</><blue>toto</>").to_string(), "\x1B[31mtoto\x1B[0m\x1B[34mtoto\x1B[0m");
-        assert_eq!(colored("toto<red>toto</><blue>toto</>").to_string(), "toto\x1B[31mtoto\x1B[0m\x1B[34mtoto\x1B[0m");
+        assert_eq!(colored("<red>toto</><blue>toto</>").to_string(), "\x1B[31mtoto\x1B[34mtoto\x1B[0m");
+        assert_eq!(colored("toto<red>toto</><blue>toto</>").to_string(), "toto\x1B[31mtoto\x1B[34mtoto\x1B[0m");
         assert_eq!(colored("<red>toto</>toto<blue>toto</>").to_string(), "\x1B[31mtoto\x1B[0mtoto\x1B[34mtoto\x1B[0m");
-        assert_eq!(colored("<red>toto</><blue>toto</>toto").to_string(), "\x1B[31mtoto\x1B[0m\x1B[34mtoto\x1B[0mtoto");
+        assert_eq!(colored("<red>toto</><blue>toto</>toto").to_string(), "\x1B[31mtoto\x1B[34mtoto\x1B[0mtoto");
         assert_eq!(colored("toto<red>toto</>toto<blue>toto</>toto").to_string(), "toto\x1B[31mtoto\x1B[0mtoto\x1B[34mtoto\x1B[0mtoto");
 
         assert_eq!(colored("<red+bold>toto</>").to_string(), "\x1B[1;31mtoto\x1B[0m");
@@ -91,33 +102,665 @@ mod tests {
     #[test]
     fn correct_assertions_subtypes()
     {
+        setup();
+
         assert_eq!(colored("<red><+bold><-></>").to_string(), "");
 
         assert_eq!(colored("<red><+bold>toto<-></>").to_string(), "\x1B[1;31mtoto\x1B[0m");
         assert_eq!(colored("<red><+blue>toto<-></>").to_string(), "\x1B[34mtoto\x1B[0m");
         assert_eq!(colored("<red><+bold+on_blue>toto<-></>").to_string(), "\x1B[1;44;31mtoto\x1B[0m");
 
-        assert_eq!(colored("<red>toto<+bold>toto<-></>").to_string(), "\x1B[31mtoto\x1B[0m\x1B[1;31mtoto\x1B[0m");
-        assert_eq!(colored("<red><+bold>toto<->toto</>").to_string(), "\x1B[1;31mtoto\x1B[0m\x1B[31mtoto\x1B[0m");
-        assert_eq!(colored("<red>toto<+bold>toto<->toto</>").to_string(), "\x1B[31mtoto\x1B[0m\x1B[1;31mtoto\x1B[0m\x1B[31mtoto\x1B[0m");
-        assert_eq!(colored("toto<red>toto<+bold>toto<->toto</>").to_string(), "toto\x1B[31mtoto\x1B[0m\x1B[1;31mtoto\x1B[0m\x1B[31mtoto\x1B[0m");
-        assert_eq!(colored("<red>toto<+bold>toto<->toto</>toto").to_string(), "\x1B[31mtoto\x1B[0m\x1B[1;31mtoto\x1B[0m\x1B[31mtoto\x1B[0mtoto");
-        assert_eq!(colored("toto<red>toto<+bold>toto<->toto</>toto").to_string(), "toto\x1B[31mtoto\x1B[0m\x1B[1;31mtoto\x1B[0m\x1B[31mtoto\x1B[0mtoto");
+        assert_eq!(colored("<red>toto<+bold>toto<-></>").to_string(), "\x1B[31mtoto\x1B[1mtoto\x1B[0m");
+        assert_eq!(colored("<red><+bold>toto<->toto</>").to_string(), "\x1B[1;31mtoto\x1B[22mtoto\x1B[0m");
+        assert_eq!(colored("<red>toto<+bold>toto<->toto</>").to_string(), "\x1B[31mtoto\x1B[1mtoto\x1B[22mtoto\x1B[0m");
+        assert_eq!(colored("toto<red>toto<+bold>toto<->toto</>").to_string(), "toto\x1B[31mtoto\x1B[1mtoto\x1B[22mtoto\x1B[0m");
+        assert_eq!(colored("<red>toto<+bold>toto<->toto</>toto").to_string(), "\x1B[31mtoto\x1B[1mtoto\x1B[22mtoto\x1B[0mtoto");
+        assert_eq!(colored("toto<red>toto<+bold>toto<->toto</>toto").to_string(), "toto\x1B[31mtoto\x1B[1mtoto\x1B[22mtoto\x1B[0mtoto");
 
-        assert_eq!(colored("<red>toto<+bold>toto<-><+italic>toto<->toto</>").to_string(), "\x1B[31mtoto\x1B[0m\x1B[1;31mtoto\x1B[0m\x1B[3;31mtoto\x1B[0m\x1B[31mtoto\x1B[0m");
-        assert_eq!(colored("<red>toto<+bold>toto<->toto<+italic>toto<->toto</>").to_string(), "\x1B[31mtoto\x1B[0m\x1B[1;31mtoto\x1B[0m\x1B[31mtoto\x1B[0m\x1B[3;31mtoto\x1B[0m\x1B[31mtoto\x1B[0m");
+        assert_eq!(colored("<red>toto<+bold>toto<-><+italic>toto<->toto</>").to_string(), "\x1B[31mtoto\x1B[1mtoto\x1B[22;3mtoto\x1B[23mtoto\x1B[0m");
+        assert_eq!(colored("<red>toto<+bold>toto<->toto<+italic>toto<->toto</>").to_string(), "\x1B[31mtoto\x1B[1mtoto\x1B[22mtoto\x1B[3mtoto\x1B[23mtoto\x1B[0m");
 
         assert_eq!(colored("<red><+bold>toto\ntoto<-></>").to_string(), "\x1B[1;31mtoto\ntoto\x1B[0m");
     }
 
+    #[test]
+    fn nested_assertions()
+    {
+        setup();
+
+        assert_eq!(colored("<red>toto<blue>toto</>toto</>").to_string(), "\x1B[31mtoto\x1B[34mtoto\x1B[31mtoto\x1B[0m");
+        assert_eq!(colored("<red>toto<bold>toto</>toto</>").to_string(), "\x1B[31mtoto\x1B[1;39mtoto\x1B[22;31mtoto\x1B[0m");
+        assert_eq!(colored("<red><blue><green>toto</>toto</>toto</>").to_string(), "\x1B[32mtoto\x1B[34mtoto\x1B[31mtoto\x1B[0m");
+        assert_eq!(colored("<red><blue>toto</></>").to_string(), "\x1B[34mtoto\x1B[0m");
+
+        assert_eq!(colored("<red><+blue>toto<+bold>toto<->toto<-></>").to_string(), "\x1B[34mtoto\x1B[1mtoto\x1B[22mtoto\x1B[0m");
+        assert_eq!(colored("<red>toto<+bold>toto<blue>toto<+italic>toto<->toto</>toto<->toto</>").to_string(), "\x1B[31mtoto\x1B[1mtoto\x1B[22;34mtoto\x1B[3mtoto\x1B[23mtoto\x1B[1;31mtoto\x1B[22mtoto\x1B[0m");
+
+        assert_eq!(colored("<red>toto<blue>toto</>").to_string(), "<red>toto\x1B[34mtoto\x1B[0m");
+        assert_eq!(colored("<red>toto</>toto</>").to_string(), "\x1B[31mtoto\x1B[0mtoto</>");
+        assert_eq!(colored("<red><+bold>toto</>").to_string(), "\x1B[31m<+bold>toto\x1B[0m");
+        assert_eq!(colored("<red>toto<->toto</>").to_string(), "\x1B[31mtoto<->toto\x1B[0m");
+        assert_eq!(colored("<red><unknown>toto</></>").to_string(), "\x1B[31m<unknown>toto</>\x1B[0m");
+    }
+
+    #[test]
+    fn removal_assertions()
+    {
+        setup();
+
+        assert_eq!(colored("<red+bold>toto<+!bold>toto<->toto</>").to_string(), "\x1B[1;31mtoto\x1B[22mtoto\x1B[1mtoto\x1B[0m");
+        assert_eq!(colored("<bold+italic>toto<+!bold>toto<->toto</>").to_string(), "\x1B[1;3mtoto\x1B[22mtoto\x1B[1mtoto\x1B[0m");
+        assert_eq!(colored("<bold><+!bold>toto<-></>").to_string(), "toto");
+        assert_eq!(colored("<red+reversed><+!reverse>toto<-></>").to_string(), "\x1B[31mtoto\x1B[0m");
+        assert_eq!(colored("<red+bold!bold>toto</>").to_string(), "<red+bold!bold>toto</>");
+
+        assert_eq!(colored("<red+on_blue><+default>toto<-></>").to_string(), "\x1B[44mtoto\x1B[0m");
+        assert_eq!(colored("<red+on_blue><+on_default>toto<-></>").to_string(), "\x1B[31mtoto\x1B[0m");
+        assert_eq!(colored("<#FF0000+bold><+default+!bold>toto<-></>").to_string(), "toto");
+        assert_eq!(colored("<red><+default+green>toto<-></>").to_string(), "\x1B[32mtoto\x1B[0m");
+        assert_eq!(colored("<red><+green+default>toto<-></>").to_string(), "toto");
+
+        assert_eq!(colored("<red+bold!>toto</>").to_string(), "<red+bold!>toto</>");
+        assert_eq!(colored("<red+!red>toto</>").to_string(), "<red+!red>toto</>");
+    }
+
+    #[test]
+    fn hex_assertions()
+    {
+        setup();
+
+        assert_eq!(colored("<#ff8000>toto</>").to_string(), "\x1B[38;2;255;128;0mtoto\x1B[0m");
+        assert_eq!(colored("<#Ff8000>toto</>").to_string(), "\x1B[38;2;255;128;0mtoto\x1B[0m");
+        assert_eq!(colored("<#F80>toto</>").to_string(), "\x1B[38;2;255;136;0mtoto\x1B[0m");
+        assert_eq!(colored("<on_#f80>toto</>").to_string(), "\x1B[48;2;255;136;0mtoto\x1B[0m");
+        assert_eq!(colored("<ON_#f80>toto</>").to_string(), "\x1B[48;2;255;136;0mtoto\x1B[0m");
+        assert_eq!(colored("<#FF80>toto</>").to_string(), "\x1B[38;2;255;255;136mtoto\x1B[0m");
+        assert_eq!(colored("<#FF00>toto</>").to_string(), "\x1B[38;2;255;255;0mtoto\x1B[0m");
+        assert_eq!(colored("<#FF000>toto</>").to_string(), "<#FF000>toto</>");
+        assert_eq!(colored("<#GG0000>toto</>").to_string(), "<#GG0000>toto</>");
+
+        // Alpha is ignored without background, blended otherwise
+        assert_eq!(colored("<#FF000080>toto</>").to_string(), "\x1B[38;2;255;0;0mtoto\x1B[0m");
+        set_alpha_background(Some((255, 255, 255)));
+        assert_eq!(colored("<#FF000080>toto</>").to_string(), "\x1B[38;2;255;127;127mtoto\x1B[0m");
+        assert_eq!(colored("<on_#F008>toto</>").to_string(), "\x1B[48;2;255;119;119mtoto\x1B[0m");
+        assert_eq!(colored("<#0000FFFF>toto</>").to_string(), "\x1B[38;2;0;0;255mtoto\x1B[0m");
+        set_alpha_background(None);
+    }
+
+    #[test]
+    fn function_assertions()
+    {
+        setup();
+
+        assert_eq!(colored("<rgb(255,128,0)>toto</>").to_string(), "\x1B[38;2;255;128;0mtoto\x1B[0m");
+        assert_eq!(colored("<RGB(255, 128, 0)>toto</>").to_string(), "\x1B[38;2;255;128;0mtoto\x1B[0m");
+        assert_eq!(colored("<rgb(100% 50% 0%)>toto</>").to_string(), "\x1B[38;2;255;128;0mtoto\x1B[0m");
+        assert_eq!(colored("<on_hsl(210,50%,40%)>toto</>").to_string(), "\x1B[48;2;51;102;153mtoto\x1B[0m");
+        assert_eq!(colored("<hsl(0 100% 50%)+bold>toto</>").to_string(), "\x1B[1;38;2;255;0;0mtoto\x1B[0m");
+        assert_eq!(colored("<oklch(0.7 0.1 200)>toto</>").to_string(), "\x1B[38;2;64;177;183mtoto\x1B[0m");
+        assert_eq!(colored("<oklch(70% 0.1 200)>toto</>").to_string(), "\x1B[38;2;64;177;183mtoto\x1B[0m");
+        assert_eq!(colored("<oklch(1 0 0)>toto</>").to_string(), "\x1B[38;2;255;255;255mtoto\x1B[0m");
+
+        // Out of gamut colors are clamped
+        assert_eq!(colored("<rgb(300,-5,0)>toto</>").to_string(), "\x1B[38;2;255;0;0mtoto\x1B[0m");
+        assert_eq!(colored("<oklch(0.5 0.4 30)>toto</>").to_string(), "\x1B[38;2;253;0;0mtoto\x1B[0m");
+
+        assert_eq!(colored("<rgb(1,2)>toto</>").to_string(), "<rgb(1,2)>toto</>");
+        assert_eq!(colored("<hsl(10%,50%,50%)>toto</>").to_string(), "<hsl(10%,50%,50%)>toto</>");
+        assert_eq!(colored("<foo(1,2,3)>toto</>").to_string(), "<foo(1,2,3)>toto</>");
+        assert_eq!(colored("a <b(c> d").to_string(), "a <b(c> d");
+    }
+
+    #[test]
+    fn named_assertions()
+    {
+        setup();
+
+        assert_eq!(colored("<orange>toto</>").to_string(), "\x1B[38;2;255;165;0mtoto\x1B[0m");
+        assert_eq!(colored("<RebeccaPurple>toto</>").to_string(), "\x1B[38;2;102;51;153mtoto\x1B[0m");
+        assert_eq!(colored("<on_teal+salmon>toto</>").to_string(), "\x1B[48;2;0;128;128;38;2;250;128;114mtoto\x1B[0m");
+        assert_eq!(colored("<red><+on_aliceblue>toto<-></>").to_string(), "\x1B[48;2;240;248;255;31mtoto\x1B[0m");
+
+        // Terminal colors have priority over CSS colors of the same name
+        assert_eq!(colored("<green>toto</>").to_string(), "\x1B[32mtoto\x1B[0m");
+        assert_eq!(colored("<on_purple>toto</>").to_string(), "\x1B[45mtoto\x1B[0m");
+
+        assert_eq!(colored("<oranges>toto</>").to_string(), "<oranges>toto</>");
+    }
+
+    #[test]
+    fn palette_assertions()
+    {
+        setup();
+
+        assert_eq!(colored("<c208>toto</>").to_string(), "\x1B[38;5;208mtoto\x1B[0m");
+        assert_eq!(colored("<C0>toto</>").to_string(), "\x1B[38;5;0mtoto\x1B[0m");
+        assert_eq!(colored("<on_c255>toto</>").to_string(), "\x1B[48;5;255mtoto\x1B[0m");
+        assert_eq!(colored("<ansi(208)>toto</>").to_string(), "\x1B[38;5;208mtoto\x1B[0m");
+        assert_eq!(colored("<on_ansi(17)+bold>toto</>").to_string(), "\x1B[1;48;5;17mtoto\x1B[0m");
+        assert_eq!(colored("<c208><+on_c17>toto<-></>").to_string(), "\x1B[48;5;17;38;5;208mtoto\x1B[0m");
+
+        assert_eq!(colored("<c256>toto</>").to_string(), "<c256>toto</>");
+        assert_eq!(colored("<c0208>toto</>").to_string(), "<c0208>toto</>");
+        assert_eq!(colored("<ansi(-1)>toto</>").to_string(), "<ansi(-1)>toto</>");
+        assert_eq!(try_colored("<c256>toto</>").unwrap_err().kind, MarkupErrorKind::UnknownStyle("c256".to_string()));
+    }
+
+    #[test]
+    fn theme_assertions()
+    {
+        setup();
+
+        let theme = Theme::new()
+            .with("error", "red+bold")
+            .with("Path", "cyan + underline")
+            .with("red", "blue")
+            .with("broken", "red+unknown");
+        assert_eq!(theme.get("ERROR"), Some("red+bold"));
+
+        assert_eq!(colored_with("<error>toto</>", &theme).to_string(), "\x1B[1;31mtoto\x1B[0m");
+        assert_eq!(colored_with("<path>toto</>", &theme).to_string(), "\x1B[4;36mtoto\x1B[0m");
+        assert_eq!(colored_with("<error+on_blue>toto<+!bold>toto<-></>", &theme).to_string(), "\x1B[1;44;31mtoto\x1B[22mtoto\x1B[0m");
+        assert_eq!(colored_with("<red>toto</>", &theme).to_string(), "\x1B[34mtoto\x1B[0m");
+        assert_eq!(colored_with("<broken>toto</>", &theme).to_string(), "<broken>toto</>");
+        assert_eq!(colored("<error>toto</>").to_string(), "<error>toto</>");
+
+        let mut theme = theme;
+        assert_eq!(theme.remove("red"), Some("blue".to_string()));
+        assert_eq!(theme.insert("error", "magenta"), Some("red+bold".to_string()));
+        assert_eq!(colored_with("<red>toto</><error>toto</>", &theme).to_string(), "\x1B[31mtoto\x1B[35mtoto\x1B[0m");
+    }
+
+    #[test]
+    fn theme_parent_assertions()
+    {
+        setup();
+
+        let base = Theme::new().with("error", "red+bold").with("path", "cyan");
+        let theme = Theme::with_parent(base.clone()).with("Path", "blue").with("hint", "green");
+        assert_eq!(theme.parent(), Some(&base));
+        assert_eq!(theme.get("error"), Some("red+bold"));
+        assert_eq!(theme.get("path"), Some("blue"));
+        assert_eq!(theme.names().collect::<Vec<_>>(), vec!["error", "hint", "path"]);
+        assert_eq!(colored_with("<error>toto</><path>toto</><hint>toto</>", &theme).to_string(), "\x1B[1;31mtoto\x1B[22;34mtoto\x1B[32mtoto\x1B[0m");
+
+        let mut theme = theme;
+        assert_eq!(theme.remove("path"), Some("blue".to_string()));
+        assert_eq!(theme.remove("error"), None);
+        assert_eq!(colored_with("<error>toto</><path>toto</>", &theme).to_string(), "\x1B[1;31mtoto\x1B[22;36mtoto\x1B[0m");
+    }
+
+    #[test]
+    fn scope_assertions()
+    {
+        setup();
+
+        let outer = Theme::new().with("label", "red").with("value", "bold");
+        let inner = Theme::with_parent(Theme::new().with("value", "green")).with("label", "blue");
+        with_styles(&outer, || {
+            assert_eq!(cformat!("<label>toto</><value>toto</>"), "\x1B[31mtoto\x1B[1;39mtoto\x1B[0m");
+            {
+                let _styles = scoped_styles(&inner);
+                assert_eq!(colored("<label>toto</><value>toto</>").to_string(), "\x1B[34mtoto\x1B[32mtoto\x1B[0m");
+                let _choice = scoped_color_choice(ColorChoice::Never);
+                assert_eq!(cformat!("<label>toto</>"), "toto");
+            }
+            assert_eq!(colored("<label>toto</><value>toto</>").to_string(), "\x1B[31mtoto\x1B[1;39mtoto\x1B[0m");
+            assert_eq!(theme().get("label"), Some("red"));
+            std::thread::spawn(|| assert_eq!(colored("<label>toto</>").to_string(), "<label>toto</>")).join().unwrap();
+        });
+        assert_eq!(colored("<label>toto</>").to_string(), "<label>toto</>");
+
+        let result = std::panic::catch_unwind(|| with_styles(&outer, || panic!("toto")));
+        assert!(result.is_err());
+        assert_eq!(colored("<label>toto</>").to_string(), "<label>toto</>");
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_assertions()
+    {
+        setup();
+
+        let theme = Theme::from_toml_str("# styles\nerror = 'red+bold'\nPath = \"cyan + underline\"\n").unwrap();
+        assert_eq!(theme, Theme::new().with("error", "red+bold").with("path", "cyan + underline"));
+        assert_eq!(colored_with("<error>toto</>", &theme).to_string(), "\x1B[1;31mtoto\x1B[0m");
+
+        let error = |text: &str| {
+            let error = Theme::from_toml_str(text).unwrap_err();
+            (error.key, error.message, error.line)
+        };
+        assert_eq!(error("error = 'red'\nwarning = 'yelow'\nhint = 'rde'"), (Some("warning".to_string()), "unknown style `yelow`".to_string(), Some(2)));
+        assert_eq!(error("\nerror = 'red+#FF00F'"), (Some("error".to_string()), "invalid hexadecimal color `#FF00F`".to_string(), Some(2)));
+        assert_eq!(error("error = 1"), (Some("error".to_string()), "expected a string".to_string(), Some(1)));
+        assert_eq!(error("error = 'red'\nerror = 'blue'").2, Some(2));
+        assert_eq!(error("error = 'red'\nerror = 'blue'").0, None);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_assertions()
+    {
+        setup();
+
+        let theme = Theme::from_json_str("{\"error\": \"red+bold\", \"Path\": \"cyan + underline\"}").unwrap();
+        assert_eq!(theme, Theme::new().with("error", "red+bold").with("path", "cyan + underline"));
+        assert_eq!(colored_with("<error>toto</>", &theme).to_string(), "\x1B[1;31mtoto\x1B[0m");
+
+        let error = |text: &str| {
+            let error = Theme::from_json_str(text).unwrap_err();
+            (error.key, error.message, error.line)
+        };
+        assert_eq!(error("{\n\"warning\": \"yelow\",\n\"error\": \"rde\"\n}"), (Some("warning".to_string()), "unknown style `yelow`".to_string(), Some(2)));
+        assert_eq!(error("{\n\"error\": \"red\",\n\"hint\": [\"red\"]\n}"), (Some("hint".to_string()), "expected a string".to_string(), Some(3)));
+        assert_eq!(error("{\n\"error\": \"red\"\n\"hint\": \"red\"\n}"), (None, "expected `,` or `}`".to_string(), Some(3)));
+        assert_eq!(error("[]").0, None);
+    }
+
+    #[test]
+    fn strict_assertions()
+    {
+        setup();
+
+        let error = |text: &str| {
+            let error = try_colored(text).unwrap_err();
+            (error.kind, error.tag, error.offset, error.line, error.column)
+        };
+
+        assert_eq!(try_colored("").unwrap().to_string(), "");
+        assert_eq!(try_colored("<red>toto</>").unwrap().to_string(), "\x1B[31mtoto\x1B[0m");
+        assert_eq!(try_colored("<red>toto<+bold>toto<-></>").unwrap().to_string(), "\x1B[31mtoto\x1B[1mtoto\x1B[0m");
+        assert_eq!(try_colored("toto<<red>toto</>>toto\\</>").unwrap().to_string(), "toto<\x1B[31mtoto\x1B[0m>toto</>");
+
+        assert_eq!(error("<rde>toto</>"), (MarkupErrorKind::UnknownStyle("rde".to_string()), "<rde>".to_string(), 0, 1, 1));
+        assert_eq!(error("toto<red+unknown>toto</>"), (MarkupErrorKind::UnknownStyle("unknown".to_string()), "<red+unknown>".to_string(), 4, 1, 5));
+        assert_eq!(error("<red><+bold+>toto<-></>"), (MarkupErrorKind::UnknownStyle("".to_string()), "<+bold+>".to_string(), 5, 1, 6));
+        assert_eq!(error("<#FF000>toto</>"), (MarkupErrorKind::BadHex("#FF000".to_string()), "<#FF000>".to_string(), 0, 1, 1));
+        assert_eq!(error("<on_#GG0000>toto</>"), (MarkupErrorKind::BadHex("on_#GG0000".to_string()), "<on_#GG0000>".to_string(), 0, 1, 1));
+        assert_eq!(error("<rgb(1,2)>toto</>"), (MarkupErrorKind::BadFunction("rgb(1,2)".to_string()), "<rgb(1,2)>".to_string(), 0, 1, 1));
+        assert_eq!(error("<on_rgb(300,0,0)>toto</>"), (MarkupErrorKind::OutOfGamut("on_rgb(300,0,0)".to_string()), "<on_rgb(300,0,0)>".to_string(), 0, 1, 1));
+        assert_eq!(error("<oklch(0.5 0.4 30)>toto</>"), (MarkupErrorKind::OutOfGamut("oklch(0.5 0.4 30)".to_string()), "<oklch(0.5 0.4 30)>".to_string(), 0, 1, 1));
+        assert_eq!(error("toto\n<red>toto"), (MarkupErrorKind::UnclosedBlock, "<red>".to_string(), 5, 2, 1));
+        assert_eq!(error("<red>\ntoto<+bold>toto</>"), (MarkupErrorKind::UnclosedBlock, "<+bold>".to_string(), 10, 2, 5));
+        assert_eq!(error("é</>"), (MarkupErrorKind::StrayCloser, "</>".to_string(), 2, 1, 2));
+        assert_eq!(error("<red>toto<-></>"), (MarkupErrorKind::StrayCloser, "<->".to_string(), 9, 1, 10));
+        assert_eq!(error("<+bold>toto<->"), (MarkupErrorKind::VariationOutsideBlock, "<+bold>".to_string(), 0, 1, 1));
+
+        assert_eq!(try_colored("toto\n<rde>toto</>").unwrap_err().to_string(), "unknown style `rde` in `<rde>` at line 2, column 1");
+    }
+
+    #[test]
+    fn check_assertions()
+    {
+        setup();
+
+        let summary = |text: &str| -> Vec<(Severity, String, Option<String>, std::ops::Range<usize>)> {
+            check(text).into_iter().map(|d| (d.severity, d.message, d.help, d.span)).collect()
+        };
+        let help = |text: &str| Some(text.to_string());
+
+        assert_eq!(summary(""), vec![]);
+        assert_eq!(summary("<red>toto<+bold>toto<->toto</>"), vec![]);
+        assert_eq!(summary("\\<red>toto"), vec![]);
+
+        assert_eq!(summary("<rde>toto</>"), vec![(Severity::Error, "unknown style `rde`".to_string(), help("did you mean `red`?"), 1..4)]);
+        assert_eq!(summary("<red+on_rde+bolt>toto</>"), vec![
+            (Severity::Error, "unknown style `on_rde`".to_string(), help("did you mean `on_red`?"), 5..11),
+            (Severity::Error, "unknown style `bolt`".to_string(), help("did you mean `bold`?"), 12..16),
+        ]);
+        assert_eq!(summary("<red><+unknown>toto<-></>"), vec![(Severity::Error, "unknown style `unknown`".to_string(), None, 7..14)]);
+        assert_eq!(summary("<on_salmom>toto</>"), vec![(Severity::Error, "unknown style `on_salmom`".to_string(), help("did you mean `on_salmon`?"), 1..10)]);
+        assert_eq!(summary("<#FF000>toto</>"), vec![(Severity::Error, "invalid hexadecimal color `#FF000`".to_string(), None, 1..7)]);
+        assert_eq!(summary("<bold+rgb(300,0,0)>toto</>"), vec![(Severity::Error, "out of gamut color `rgb(300,0,0)`".to_string(), help("clamped to `#FF0000`"), 6..18)]);
+
+        assert_eq!(summary("<blue+red>toto</>"), vec![(Severity::Warning, "style `blue` is overridden".to_string(), help("remove `blue`, overridden by `red`"), 1..5)]);
+        assert_eq!(summary("<on_blue+bold+on_#FF0000>toto</>"), vec![(Severity::Warning, "style `on_blue` is overridden".to_string(), help("remove `on_blue`, overridden by `on_#FF0000`"), 1..8)]);
+        assert_eq!(summary("<red><+bold+!bold>toto<-></>"), vec![(Severity::Warning, "style `bold` is overridden".to_string(), help("remove `bold`, overridden by `!bold`"), 7..11)]);
+        assert_eq!(summary("<reverse+reversed>toto</>"), vec![(Severity::Warning, "style `reverse` is overridden".to_string(), help("remove `reverse`, overridden by `reversed`"), 1..8)]);
+        assert_eq!(summary("<red+default>toto</>"), vec![(Severity::Warning, "style `red` is overridden".to_string(), help("remove `red`, overridden by `default`"), 1..4)]);
+
+        assert_eq!(summary("<red></>"), vec![(Severity::Warning, "empty block".to_string(), help("remove the block"), 0..8)]);
+        assert_eq!(summary("<red>toto<+bold><-></>"), vec![(Severity::Warning, "empty block".to_string(), help("remove the block"), 9..19)]);
+
+        assert_eq!(summary("<red>toto<+bold>toto</>"), vec![(Severity::Error, "unclosed block".to_string(), help("add a closing flag or escape it as `\\<+bold>`"), 9..16)]);
+        assert_eq!(summary("toto</>"), vec![(Severity::Error, "closing flag without opening flag".to_string(), help("remove it or escape it as `\\</>`"), 4..7)]);
+        assert_eq!(summary("<+bold>toto<->").len(), 2);
+
+        let text = "<red>toto\n<blue+red>toto</>\n<rde>toto</>";
+        let diagnostics = check(text);
+        assert_eq!(diagnostics.iter().map(|d| (d.line, d.column)).collect::<Vec<_>>(), vec![(1, 1), (2, 2), (3, 2)]);
+        assert_eq!(diagnostics[0].to_string(), "error: unclosed block at line 1, column 1");
+        assert_eq!(report(text, &diagnostics), "\
+error: unclosed block
+ --> line 1, column 1
+  |
+1 | <red>toto
+  | ^^^^^ add a closing flag or escape it as `\\<red>`
+warning: style `blue` is overridden
+ --> line 2, column 2
+  |
+2 | <blue+red>toto</>
+  |  ^^^^ remove `blue`, overridden by `red`
+error: unknown style `rde`
+ --> line 3, column 2
+  |
+3 | <rde>toto</>
+  |  ^^^ did you mean `red`?
+");
+    }
+
+    #[test]
+    fn parse_tree()
+    {
+        setup();
+
+        assert_eq!(parse("").nodes, vec![]);
+        assert_eq!(parse("toto").nodes, vec![
+            Node::Text(Text { text: "toto".to_string(), span: 0..4 })
+        ]);
+        assert_eq!(parse("<red>toto").nodes, vec![
+            Node::Text(Text { text: "<red>toto".to_string(), span: 0..9 })
+        ]);
+        assert_eq!(parse("to<red>to<+bold>to<->to</>").nodes, vec![
+            Node::Text(Text { text: "to".to_string(), span: 0..2 }),
+            Node::Block(Block {
+                styles: "red".to_string(),
+                children: vec![
+                    Node::Text(Text { text: "to".to_string(), span: 7..9 }),
+                    Node::Variation(Block {
+                        styles: "bold".to_string(),
+                        children: vec![Node::Text(Text { text: "to".to_string(), span: 16..18 })],
+                        span: 9..21,
+                        open_span: 9..16,
+                        close_span: 18..21
+                    }),
+                    Node::Text(Text { text: "to".to_string(), span: 21..23 }),
+                ],
+                span: 2..26,
+                open_span: 2..7,
+                close_span: 23..26
+            })
+        ]);
+        assert_eq!(parse("<red><unknown>to</>to</>").nodes[0].span(), 0..24);
+
+        let sources = ["", "toto", "<red>toto</>", "<red>to<+bold>to<->to</>", "<red>to<blue>to</>", "<+bold>to<->", "<red><unknown>to</></>"];
+        for source in sources {
+            assert_eq!(colored(&parse(source).to_string()).to_string(), colored(source).to_string());
+            assert_eq!(parse(source).colored().to_string(), colored(source).to_string());
+        }
+        assert_eq!(parse("<red>to<+bold>to<->to</>").to_string(), "<red>to<+bold>to<->to</>");
+        assert_eq!(parse("<red>to<blue>to</>").to_string(), "\\<red>to<blue>to</>");
+        assert_eq!(parse("a</>b").to_string(), "a\\</>b");
+        assert_eq!(colored(&parse("a</>b").to_string()).to_string(), colored("a</>b").to_string());
+
+        let mut markup = parse("<red>toto</>");
+        if let Node::Block(block) = &mut markup.nodes[0] {
+            block.styles = "blue".to_string();
+        }
+        assert_eq!(markup.to_string(), "<blue>toto</>");
+        assert_eq!(markup.colored().to_string(), "\x1B[34mtoto\x1B[0m");
+    }
+
+    #[test]
+    fn escape_assertions()
+    {
+        setup();
+
+        assert_eq!(colored("\\<red>toto</>").to_string(), "<red>toto</>");
+        assert_eq!(colored("<red>\\</>toto</>").to_string(), "\x1B[31m</>toto\x1B[0m");
+        assert_eq!(colored("<red>Vec\\<u8></>").to_string(), "\x1B[31mVec<u8>\x1B[0m");
+        assert_eq!(colored("<red>\\\\</>").to_string(), "\x1B[31m\\\x1B[0m");
+        assert_eq!(colored("<red>C:\\dir</>").to_string(), "\x1B[31mC:\\dir\x1B[0m");
+        assert_eq!(colored("\\\\<red>toto</>").to_string(), "\\\x1B[31mtoto\x1B[0m");
+        assert_eq!(colored("toto\\").to_string(), "toto\\");
+        assert_eq!(colored("\\\\server\\share").to_string(), "\\server\\share");
+
+        assert_eq!(escape("toto"), "toto");
+        assert_eq!(escape("<red>toto</>"), "\\<red>toto\\</>");
+        assert_eq!(escape("C:\\dir\\"), "C:\\\\dir\\\\");
+
+        let texts = ["<red>toto</>", "Vec<u8>", "cat < in > out", "C:\\dir\\", "\\<", "<+bold><->"];
+        for text in texts {
+            assert_eq!(colored(&escape(text)).to_string(), text);
+            assert_eq!(colored(&format!("<red>{}</>", escape(text))).to_string(), format!("\x1B[31m{}\x1B[0m", text));
+        }
+    }
+
+    #[test]
+    fn macro_arguments()
+    {
+        setup();
+
+        let toto = "toto";
+        let red = "<red>toto</>";
+        let width = 6;
+
+        assert_eq!(cformat!("{}", red), "<red>toto</>");
+        assert_eq!(cformat!("{red}"), "<red>toto</>");
+        assert_eq!(cformat!("{text}", text = red), "<red>toto</>");
+        assert_eq!(cformat!("<blue>{}</>", red), "\x1B[34m<red>toto</>\x1B[0m");
+        assert_eq!(cformat!("<blue>{}</>", "</>toto"), "\x1B[34m</>toto\x1B[0m");
+        assert_eq!(cformat!("<blue>{}</>", "\\"), "\x1B[34m\\\x1B[0m");
+        assert_eq!(cformat!("<blue>{:?}</>", "<red>"), "\x1B[34m\"<red>\"\x1B[0m");
+
+        assert_eq!(cformat!("{:markup}", red), "\x1B[31mtoto\x1B[0m");
+        assert_eq!(cformat!("{red:markup}"), "\x1B[31mtoto\x1B[0m");
+        assert_eq!(cformat!("<blue>{}{:markup}</>", toto, "<+bold>toto<->"), "\x1B[34mtoto\x1B[1mtoto\x1B[0m");
+
+        assert_eq!(cformat!("<red>{:>6}</>", toto), "\x1B[31m  toto\x1B[0m");
+        assert_eq!(cformat!("<red>{:-<6}</>", toto), "\x1B[31mtoto--\x1B[0m");
+        assert_eq!(cformat!("<red>{toto:>width$}</>"), "\x1B[31m  toto\x1B[0m");
+        assert_eq!(cformat!("<red>{:>1$}</>", toto, width), "\x1B[31m  toto\x1B[0m");
+        assert_eq!(cformat!("<red>{:.*}</>", 2, 1.2345), "\x1B[31m1.23\x1B[0m");
+        assert_eq!(cformat!("<red>{:+.1}</>", 1.25), "\x1B[31m+1.2\x1B[0m");
+        assert_eq!(cformat!("<red>{:#06x}</>", 255), "\x1B[31m0x00ff\x1B[0m");
+        assert_eq!(cformat!("<red>{1}{0}{1}</>", "a", "b"), "\x1B[31mbab\x1B[0m");
+        assert_eq!(cformat!("<red>{{{}}}</>", toto), "\x1B[31m{toto}\x1B[0m");
+        assert_eq!(cformat!("<red>{}</>", toto,), "\x1B[31mtoto\x1B[0m");
+
+        let mut count = 0;
+        let mut next = || { count += 1; count };
+        assert_eq!(cformat!("<red>{0}{0}</>", next()), "\x1B[31m11\x1B[0m");
+    }
+
+    #[test]
+    fn compile_check_assertions()
+    {
+        setup();
+
+        // Templates accepted by the compile time checks, invalid ones being tested by doc tests
+        let color = "blue";
+        let theme = Theme::new().with("warning", "yellow+bold");
+        assert_eq!(cformat!("<{color}>toto</>"), "\x1B[34mtoto\x1B[0m");
+        assert_eq!(cformat!("<red+on_{}>toto</>", color), "\x1B[44;31mtoto\x1B[0m");
+        assert_eq!(cformat!("<rgb({}, 0, 0)>toto</>", 255), "\x1B[38;2;255;0;0mtoto\x1B[0m");
+        assert_eq!(cformat!("\\<red>toto"), "<red>toto");
+        assert_eq!(cformat!("<red>{:markup}", "toto</>"), "\x1B[31mtoto\x1B[0m");
+        assert_eq!(cformat!("<On_Red+c208+ansi(17)+#F00A+!Bold+teal+on_gold>toto</>"), colored("<On_Red+c208+ansi(17)+#F00A+!Bold+teal+on_gold>toto</>").to_string());
+        with_styles(&theme, || assert_eq!(cformat!("<warning>toto</>"), "\x1B[1;33mtoto\x1B[0m"));
+        assert_eq!(cformat!(r"<red>{{toto}}</>"), "\x1B[31m{toto}\x1B[0m");
+
+        // Flags that do not match are only reported if they surely hold styles
+        assert_eq!(cformat!("Vec<{}>", "u8"), "Vec<u8>");
+        assert_eq!(cformat!("a <div>x"), "a <div>x");
+        assert_eq!(cformat!("<red>a</> <T>"), "\x1B[31ma\x1B[0m <T>");
+        assert_eq!(cformat!("<+{}>toto", color), "<+blue>toto");
+        with_styles(&theme, || assert_eq!(cformat!("<warning>toto"), "<warning>toto"));
+    }
+
+    #[test]
+    fn baked_assertions()
+    {
+        setup();
+
+        // Scoped styles force rendering at runtime, no other test uses the name defined here
+        let runtime = Theme::new().with("baked", "red");
+        macro_rules! assert_baked {
+            ($($arg:tt)*) => {
+                let baked = cformat!($($arg)*);
+                assert_eq!(baked, with_styles(&runtime, || cformat!($($arg)*)));
+                let plain = with_color_choice(ColorChoice::Never, || cformat!($($arg)*));
+                assert_eq!(plain, with_styles(&runtime, || with_color_choice(ColorChoice::Never, || cformat!($($arg)*))));
+            };
+        }
+
+        assert_baked!("<red>toto</>");
+        assert_baked!("<Red+ON_lblue+bold+underline+reverse>toto<+!bold+default>toto<-></>");
+        assert_baked!("<#F80+on_#123456>toto</> <c208+on_c17>toto</> <italic+dimmed>toto</>");
+        assert_baked!("<red>{}</><blue>{:>6}</><green>{:?}</>", "", "toto", "<red>");
+        assert_baked!("<red>\\<toto \\\\ {{}}</> {} toto", 1);
+        assert_baked!("<red>toto\x1B[0mtoto{}</>", "\x1B[0mtoto");
+        assert_baked!("<red>{toto}<+on_blue>{toto:.1}<-></>", toto = 1.25);
+        assert_baked!("<red>toto</>{}<blue>toto</>{}<blue>toto</>", "", "");
+        assert_baked!("<bold>toto<+dimmed>toto<+!bold>toto<->toto<-></>{}", "\x1B[1mtoto");
+        assert_baked!("<red>toto {} <+bold>{}<-></>{}", "\x1B[1mtoto\x1B[22;39m", "\x1B[38;2;0;0;0mtoto\x1B[m", "\x1B[2K");
+        assert_eq!(cformat!("<red>{}</>toto", ""), "toto");
+        assert_eq!(cformat!("<red>toto\x1B[0m{}</>", "\x1B[0m"), "\x1B[31mtoto\x1B[0m\x1B[0m");
+
+        // Decorations are baked as bits in the order of the table shared with the macros
+        for (name, code) in colored_str_syntax::DECORATIONS {
+            assert_eq!(colored(&format!("<{}>toto</>", name)).to_string(), format!("\x1B[{}mtoto\x1B[0m", code));
+        }
+        assert_baked!("<bold>toto</><dimmed>toto</><underline>toto</><reversed>toto</>");
+        assert_baked!("<italic>toto</><blink>toto</><hidden>toto</><strikethrough>toto</>");
+        assert_baked!("<strikethrough+hidden+blink+italic+reverse+underline+dimmed+bold>toto<+!dimmed+!hidden>toto<-></>");
+    }
+
+    #[test]
+    fn template_assertions()
+    {
+        setup();
+
+        let template = Template::compile("<red>{}</> <+bold>{}</> <blue>{name}<+bold>{{{0}}}<-></>").unwrap();
+        let text = template.render(Args::new().arg(&1).arg(&"<red>").named("name", &2.5)).to_string();
+        assert_eq!(text, "\x1B[31m1\x1B[0m <+bold><red></> \x1B[34m2.5\x1B[1m{1}\x1B[0m");
+        let text = template.render(Args::new().arg(&1)).to_string();
+        assert_eq!(text, "\x1B[31m1\x1B[0m <+bold>{}</> \x1B[34m{name}\x1B[1m{1}\x1B[0m");
+        assert_eq!(format!("{}", template.render(Args::new())), template.render(Args::new()).to_string());
+
+        let theme = Theme::new().with("count", "green");
+        let template = Template::compile_with("<count>{}</> <rde>\\<{}</>", &theme).unwrap();
+        assert_eq!(template.render(Args::new().arg(&3).arg(&4)).to_string(), "\x1B[32m3\x1B[0m <rde><4</>");
+        let text = with_color_choice(ColorChoice::Never, || template.render_for(Args::new().arg(&3).arg(&4), Stream::Stderr).to_string());
+        assert_eq!(text, "3 <rde><4</>");
+
+        let error = Template::compile("<red>{}</>\n{na-me}").unwrap_err();
+        assert_eq!(error.kind, MarkupErrorKind::BadPlaceholder("{na-me}".to_string()));
+        assert_eq!((error.offset, error.line, error.column), (11, 2, 1));
+        let error = Template::compile("<red>{0</>").unwrap_err();
+        assert_eq!(error.kind, MarkupErrorKind::BadPlaceholder("{0</>".to_string()));
+        let error = Template::compile("<red>}</>").unwrap_err();
+        assert_eq!((error.kind, error.offset), (MarkupErrorKind::BadPlaceholder("}".to_string()), 5));
+        let error = Template::compile("<red>{:>6} {0:?}</>").unwrap_err();
+        assert_eq!(error.kind, MarkupErrorKind::BadPlaceholder("{:>6}".to_string()));
+    }
+
+    #[test]
+    fn diff_assertions()
+    {
+        setup();
+
+        assert_eq!(colored("<red>toto</><red>toto</>").to_string(), "\x1B[31mtotototo\x1B[0m");
+        assert_eq!(colored("<red>toto<+red>toto<-><unknown>toto</></>").to_string(), "\x1B[31mtotototo<unknown>toto</>\x1B[0m");
+        assert_eq!(colored("<red>toto<+on_blue>toto<->toto</>").to_string(), "\x1B[31mtoto\x1B[44mtoto\x1B[49mtoto\x1B[0m");
+        assert_eq!(colored("<bold+dimmed>toto<+!bold>toto<->toto</>").to_string(), "\x1B[1;2mtoto\x1B[22;2mtoto\x1B[1mtoto\x1B[0m");
+        assert_eq!(colored("<bold+underline+red+on_blue>toto<+!bold+!underline+default>toto<-></>").to_string(), "\x1B[1;4;44;31mtoto\x1B[22;24;39mtoto\x1B[0m");
+        assert_eq!(colored("<bold+underline+red>toto<+!bold+!underline+default>toto<-></>").to_string(), "\x1B[1;4;31mtoto\x1B[0mtoto");
+
+        // Escape sequences within a styled text leave the terminal state unknown until the next reset
+        assert_eq!(colored("<red>toto\x1B[1m</><blue>toto</>").to_string(), "\x1B[31mtoto\x1B[1m\x1B[0;34mtoto\x1B[0m");
+        assert_eq!(colored("<red>toto\x1B[1m</>toto").to_string(), "\x1B[31mtoto\x1B[1m\x1B[0mtoto");
+        assert_eq!(colored("<red>\x1B[1mtoto\x1B[0mtoto</><blue>toto</>").to_string(), "\x1B[31m\x1B[1mtoto\x1B[0m\x1B[31mtoto\x1B[34mtoto\x1B[0m");
+    }
+
+    #[test]
+    fn nested_colored_assertions()
+    {
+        setup();
+        // Only colorizes the `ColoredString` values made with colored itself, such as the first `inner`
+        colored::control::set_override(true);
+
+        let inner = colored::Colorize::bold(colored::Colorize::blue("toto"));
+        let text = cformat!("<red>toto {} toto</>", inner);
+        assert_eq!(text, "\x1B[31mtoto \x1B[1;34mtoto\x1B[0m\x1B[31m toto\x1B[0m");
+        assert_eq!(text, colored(&format!("<red>toto {} toto</>", inner)).to_string());
+        let inner = colored("<blue>toto</>");
+        assert_eq!(cformat!("<red>{} <+bold>{}<-> toto</>", inner, inner), "\x1B[31m\x1B[34mtoto\x1B[0m\x1B[31m \x1B[1m\x1B[34mtoto\x1B[0m\x1B[31m toto\x1B[0m");
+        let template = Template::compile("<red>{} toto</>").unwrap();
+        assert_eq!(template.render(Args::new().arg(&inner)).to_string(), "\x1B[31m\x1B[34mtoto\x1B[0m\x1B[31m toto\x1B[0m");
+
+        assert_eq!(cformat!("<red>toto {} toto</>", "\x1B[1mtoto\x1B[m"), "\x1B[31mtoto \x1B[1mtoto\x1B[m\x1B[31m toto\x1B[0m");
+        assert_eq!(cformat!("<red>toto {} toto</>", "\x1B[1mtoto\x1B[22;39m"), "\x1B[31mtoto \x1B[1mtoto\x1B[22;39m\x1B[0;31m toto\x1B[0m");
+        assert_eq!(cformat!("<red>toto {}</><blue>toto</>", "\x1B[1mtoto\x1B[22m"), "\x1B[31mtoto \x1B[1mtoto\x1B[22m\x1B[0;34mtoto\x1B[0m");
+        assert_eq!(cformat!("<red>toto {} toto</>", "\x1B[38;2;0;0;0mtoto\x1B[48;5;0m"), "\x1B[31mtoto \x1B[38;2;0;0;0mtoto\x1B[48;5;0m toto\x1B[0m");
+        assert_eq!(cformat!("<red>toto {} toto</>", "\x1B[2Ktoto"), "\x1B[31mtoto \x1B[2Ktoto toto\x1B[0m");
+
+        /// Text written with its reset split between writes
+        struct Split;
+        impl std::fmt::Display for Split
+        {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result
+            {
+                f.write_str("\x1B[34mtoto\x1B")?;
+                f.write_str("[")?;
+                f.write_str("0m")
+            }
+        }
+        assert_eq!(cformat!("<red>{} toto</>", Split), "\x1B[31m\x1B[34mtoto\x1B[0m\x1B[31m toto\x1B[0m");
+        assert_eq!(cformat!("<red>{}</>", Split), "\x1B[31m\x1B[34mtoto\x1B[0m");
+    }
+
+    #[test]
+    fn write_assertions()
+    {
+        setup();
+
+        let mut text = String::from("> ");
+        write_colored(&mut text, "<red>red <+bold>bold<-> \x1B[0mred</> <rde>kept</>", Stream::Stdout).unwrap();
+        assert_eq!(text, "> \x1B[31mred \x1B[1mbold\x1B[22m \x1B[0m\x1B[31mred\x1B[0m <rde>kept</>");
+
+        let markup = "<dimmed>line</> <yellow+bold>with <+on_blue>nested<-> <#FF8000>styles</></> \\<kept>\n".repeat(1000);
+        let mut text = String::new();
+        write_colored(&mut text, &markup, Stream::Stdout).unwrap();
+        assert_eq!(text, colored(&markup).to_string());
+        assert_eq!(strip_ansi(&text), strip(&markup));
+    }
+
+    #[test]
+    fn strip_assertions()
+    {
+        setup();
+
+        assert_eq!(strip(""), "");
+        assert_eq!(strip("toto"), "toto");
+        assert_eq!(strip("<red>toto</>"), "toto");
+        assert_eq!(strip("toto<red+bold>toto<+on_blue>toto<-></>toto"), "totototototototo");
+        assert_eq!(strip("<red>toto<blue>toto</>toto</>"), "totototototo");
+        assert_eq!(strip("<rde>toto</>"), "<rde>toto</>");
+        assert_eq!(strip("<red>toto"), "<red>toto");
+        assert_eq!(strip("toto</>"), "toto</>");
+        assert_eq!(strip("<red>Vec\\<u8> \\\\</>"), "Vec<u8> \\");
+        assert_eq!("<red>toto</>".stripped(), "toto");
+        assert_eq!(parse("<red>toto</><rde>toto</>").stripped(), "toto<rde>toto</>");
+
+        assert_eq!(strip_ansi("toto"), "toto");
+        assert_eq!(strip_ansi(&colored("<red>toto<+bold>toto<-></>toto").to_string()), "totototototo");
+        assert_eq!(strip_ansi(&colored("<#FF0000+on_c17>toto</>").to_string()), "toto");
+        assert_eq!(strip_ansi("\x1B]8;;https://example.com\x1B\\toto\x1B]8;;\x07"), "toto");
+        assert_eq!(strip_ansi("toto\x1B"), "toto");
+    }
+
     #[test]
     fn str_trait()
     {
+        setup();
+
         assert_eq!("<red></>".colored().to_string(), "");
         assert_eq!("<red>toto</>".colored().to_string(), "\x1B[31mtoto\x1B[0m");
         assert_eq!("<#FF0000>toto</>".colored().to_string(), "\x1B[38;2;255;0;0mtoto\x1B[0m");
         assert_eq!("<on_#FF0000>toto</>".colored().to_string(), "\x1B[48;2;255;0;0mtoto\x1B[0m");
-        assert_eq!("<red>toto</><blue>toto</>".colored().to_string(), "\x1B[31mtoto\x1B[0m\x1B[34mtoto\x1B[0m");
+        assert_eq!("<red>toto</><blue>toto</>".colored().to_string(), "\x1B[31mtoto\x1B[34mtoto\x1B[0m");
         assert_eq!("<red+bold>toto</>".colored().to_string(), "\x1B[1;31mtoto\x1B[0m");
         assert_eq!("<red+blue>toto</>".colored().to_string(), "\x1B[34mtoto\x1B[0m");
         assert_eq!("<bold+bold>toto</>".colored().to_string(), "\x1B[1mtoto\x1B[0m");
@@ -125,14 +768,16 @@ mod tests {
 
         assert_eq!("<red><+bold><-></>".colored().to_string(), "");
         assert_eq!("<red><+bold>toto<-></>".colored().to_string(), "\x1B[1;31mtoto\x1B[0m");
-        assert_eq!("<red>toto<+bold>toto<-></>".colored().to_string(), "\x1B[31mtoto\x1B[0m\x1B[1;31mtoto\x1B[0m");
-        assert_eq!("<red>toto<+bold>toto<-><+italic>toto<->toto</>".colored().to_string(), "\x1B[31mtoto\x1B[0m\x1B[1;31mtoto\x1B[0m\x1B[3;31mtoto\x1B[0m\x1B[31mtoto\x1B[0m");
+        assert_eq!("<red>toto<+bold>toto<-></>".colored().to_string(), "\x1B[31mtoto\x1B[1mtoto\x1B[0m");
+        assert_eq!("<red>toto<+bold>toto<-><+italic>toto<->toto</>".colored().to_string(), "\x1B[31mtoto\x1B[1mtoto\x1B[22;3mtoto\x1B[23mtoto\x1B[0m");
         assert_eq!("<red><+bold>toto\ntoto<-></>".colored().to_string(), "\x1B[1;31mtoto\ntoto\x1B[0m");
     }
 
     #[test]
     fn str_macro()
     {
+        setup();
+
         let toto = "toto";
 
         assert_eq!(cformat!(), "");
@@ -141,15 +786,15 @@ mod tests {
 
         assert_eq!(cformat!("<#FF0000>{toto}</>"), "\x1B[38;2;255;0;0mtoto\x1B[0m");
         assert_eq!(cformat!("<on_#FF0000>{toto}</>"), "\x1B[48;2;255;0;0mtoto\x1B[0m");
-        assert_eq!(cformat!("<red>{toto}</><blue>{toto}</>"), "\x1B[31mtoto\x1B[0m\x1B[34mtoto\x1B[0m");
+        assert_eq!(cformat!("<red>{toto}</><blue>{toto}</>"), "\x1B[31mtoto\x1B[34mtoto\x1B[0m");
         assert_eq!(cformat!("<red+bold>{toto}</>"), "\x1B[1;31mtoto\x1B[0m");
         assert_eq!(cformat!("<red+blue>{toto}</>"), "\x1B[34mtoto\x1B[0m");
         assert_eq!(cformat!("<bold+bold>{toto}</>"), "\x1B[1mtoto\x1B[0m");
         assert_eq!(cformat!("<red>{toto}\n{toto}</>"), "\x1B[31mtoto\ntoto\x1B[0m");
 
         assert_eq!(cformat!("<red><+bold>{toto}<-></>"), "\x1B[1;31mtoto\x1B[0m");
-        assert_eq!(cformat!("<red>{toto}<+bold>{toto}<-></>"), "\x1B[31mtoto\x1B[0m\x1B[1;31mtoto\x1B[0m");
-        assert_eq!(cformat!("<red>{toto}<+bold>{toto}<-><+italic>{toto}<->{toto}</>"), "\x1B[31mtoto\x1B[0m\x1B[1;31mtoto\x1B[0m\x1B[3;31mtoto\x1B[0m\x1B[31mtoto\x1B[0m");
+        assert_eq!(cformat!("<red>{toto}<+bold>{toto}<-></>"), "\x1B[31mtoto\x1B[1mtoto\x1B[0m");
+        assert_eq!(cformat!("<red>{toto}<+bold>{toto}<-><+italic>{toto}<->{toto}</>"), "\x1B[31mtoto\x1B[1mtoto\x1B[22;3mtoto\x1B[23mtoto\x1B[0m");
         assert_eq!(cformat!("<red><+bold>{toto}\n{toto}<-></>"), "\x1B[1;31mtoto\ntoto\x1B[0m");
     }
 